@@ -2,5 +2,10 @@
 #include "csp/csp_hooks.h"
 #include "csp_conn.h"
 
+#include "csp/crypto/csp_hmac.h"
+#include "csp/drivers/can_socketcan.h"
+#include "csp/interfaces/csp_if_kiss.h"
 #include "csp/interfaces/csp_if_lo.h"
+#include "csp/interfaces/csp_if_tun.h"
 #include "csp/interfaces/csp_if_udp.h"
+#include "csp/interfaces/csp_if_zmqhub.h"
@@ -15,6 +15,8 @@ fn main() {
         .expect("creating libcsp builder failed");
     // A lot of spam we are not interested in usually.
     csp_builder.compiler_warnings = false;
+    csp_builder.cfg.can = env::var_os("CARGO_FEATURE_CAN").is_some();
+    csp_builder.cfg.zmq = env::var_os("CARGO_FEATURE_ZMQ").is_some();
 
     // We always re-generate the header file.
     generate_autoconf_header_file(manifest_path.clone(), &csp_builder.cfg)
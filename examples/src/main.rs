@@ -1,5 +1,6 @@
 use std::{
     ffi::CStr,
+    io::Write,
     sync::{
         atomic::{AtomicBool, AtomicU32},
         Arc,
@@ -9,10 +10,9 @@ use std::{
 };
 
 use libcsp::{
-    csp_accept_guarded, csp_bind, csp_buffer_get, csp_conn_dport, csp_conn_print_table,
-    csp_connect_guarded, csp_init, csp_listen, csp_ping, csp_read_guarded, csp_reboot,
-    csp_route_work, csp_send, csp_service_handler, iflist::csp_iflist_print, ConnectOpts, CspError,
-    CspSocket, MsgPriority, SocketFlags, CSP_ANY, CSP_LOOPBACK,
+    csp_conn_dport, csp_conn_print_table, csp_read, csp_send, csp_service_handler,
+    iflist::csp_iflist_print, ConnectOpts, CspConfig, CspStack, ErrorCode, MsgPriority, Socket,
+    SocketFlags, CSP_ANY, CSP_LOOPBACK,
 };
 
 const MY_SERVER_PORT: i32 = 10;
@@ -22,8 +22,7 @@ const RUN_DURATION_IN_SECS: u32 = 3;
 fn main() -> Result<(), u32> {
     println!("CSP client/server example");
 
-    // SAFETY: We only call this once.
-    unsafe { csp_init() };
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
 
     let stop_signal = Arc::new(AtomicBool::new(false));
     let stop_signal_server = stop_signal.clone();
@@ -36,11 +35,11 @@ fn main() -> Result<(), u32> {
         if stop_signal_router.load(std::sync::atomic::Ordering::Relaxed) {
             break;
         }
-        if let Err(e) = csp_route_work() {
-            match e {
-                CspError::TimedOut => continue,
-                e => {
-                    println!("CSP router error: {:?}", e);
+        if let Err(e) = csp.route_work() {
+            match e.code() {
+                ErrorCode::TimedOut => continue,
+                _ => {
+                    println!("CSP router error: {}", e);
                     break;
                 }
             }
@@ -48,11 +47,11 @@ fn main() -> Result<(), u32> {
     });
 
     let csp_server_jh = thread::spawn(move || {
-        server(server_received, stop_signal_server);
+        server(csp, server_received, stop_signal_server);
     });
 
     let csp_client_jh = thread::spawn(move || {
-        client(stop_signal_client);
+        client(csp, stop_signal_client);
     });
 
     println!("CSP connection table");
@@ -83,18 +82,22 @@ fn main() -> Result<(), u32> {
     app_result
 }
 
-fn server(server_received: Arc<AtomicU32>, stop_signal: Arc<AtomicBool>) {
+fn server(csp: CspStack, server_received: Arc<AtomicU32>, stop_signal: Arc<AtomicBool>) {
     println!("server task started");
 
     // Create socket with no specific socket options, e.g. accepts CRC32, HMAC, etc. if enabled
     // during compilation
-    let mut csp_socket = CspSocket::default();
+    let mut csp_socket = Socket::new(SocketFlags::NONE);
 
     // Bind socket to all ports, e.g. all incoming connections will be handled here
-    csp_bind(&mut csp_socket, CSP_ANY);
+    csp_socket
+        .bind(&csp, CSP_ANY)
+        .expect("binding CSP socket failed");
 
     // Create a backlog of 10 connections, i.e. up to 10 new connections can be queued
-    csp_listen(&mut csp_socket, 10);
+    csp_socket
+        .listen(10)
+        .expect("listening on CSP socket failed");
 
     // Wait for connections and then process packets on the connection
     loop {
@@ -103,8 +106,8 @@ fn server(server_received: Arc<AtomicU32>, stop_signal: Arc<AtomicBool>) {
         }
 
         // Wait for a new connection, 10000 mS timeout
-        let conn = csp_accept_guarded(&mut csp_socket, Duration::from_millis(10000));
-        if conn.is_none() {
+        let conn = csp_socket.accept(Duration::from_millis(10000));
+        if conn.is_err() {
             continue;
         }
         let mut conn = conn.unwrap();
@@ -115,22 +118,22 @@ fn server(server_received: Arc<AtomicU32>, stop_signal: Arc<AtomicBool>) {
                 break;
             }
 
-            // Guarded packet is cleaned up automatically.
-            let packet = csp_read_guarded(&mut conn.0, Duration::from_millis(100));
-            if packet.is_none() {
+            // The packet is returned to the buffer pool automatically when it is dropped.
+            let packet = csp_read(&mut conn.0, Duration::from_millis(100));
+            if packet.is_err() {
                 break;
             }
             let packet = packet.unwrap();
             match csp_conn_dport(&conn.0) {
                 MY_SERVER_PORT => {
                     server_received.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                    let cstr = CStr::from_bytes_with_nul(packet.as_ref().packet_data())
+                    let cstr = CStr::from_bytes_with_nul(&packet)
                         .expect("invalid packet data format, is not C string");
                     // Process packet here.
                     println!("packet received on MY_SERVER_PORT: {:?}", cstr);
                 }
                 _ => {
-                    csp_service_handler(packet.take());
+                    csp_service_handler(packet);
                 }
             };
         }
@@ -138,7 +141,7 @@ fn server(server_received: Arc<AtomicU32>, stop_signal: Arc<AtomicBool>) {
     }
 }
 
-fn client(stop_signal: Arc<AtomicBool>) {
+fn client(csp: CspStack, stop_signal: Arc<AtomicBool>) {
     println!("client task started");
     let mut current_letter = 'A';
 
@@ -153,53 +156,50 @@ fn client(stop_signal: Arc<AtomicBool>) {
         }
 
         // Send ping to server, timeout 1000 mS, ping size 20 bytes
-        if let Err(e) = csp_ping(
+        if let Err(e) = csp.ping(
             CSP_LOOPBACK,
             Duration::from_millis(1000),
             20,
             SocketFlags::NONE,
         ) {
-            println!("ping error: {:?}", e);
+            println!("ping error: {}", e);
         }
 
         // Send reboot request to server, the server has no actual implementation of
         // csp_sys_reboot() and fails to reboot.
-        csp_reboot(CSP_LOOPBACK);
+        csp.reboot(CSP_LOOPBACK);
 
         // Send data packet (string) to server
 
         // 1. Connect to host on 'server_address', port MY_SERVER_PORT with regular UDP-like
         // protocol and 1000 ms timeout.
-        let conn = csp_connect_guarded(
+        let conn = csp.connect_guarded(
             MsgPriority::Normal,
             CSP_LOOPBACK,
             MY_SERVER_PORT as u8,
             Duration::from_millis(1000),
             ConnectOpts::NONE,
         );
-        if conn.is_none() {
-            println!("CSP client: connection failed");
+        if let Err(e) = conn {
+            println!("CSP client: connection failed: {}", e);
             return;
         }
         let mut conn = conn.unwrap();
 
         // 2. Get packet buffer for message/data.
-        let packet_ref = csp_buffer_get();
-        if packet_ref.is_none() {
-            println!("CSP client: failed to get CSP buffer");
+        let packet = csp.buffer_get();
+        if let Err(e) = packet {
+            println!("CSP client: failed to get CSP buffer: {}", e);
             return;
         }
-        let mut packet_mut = packet_ref.unwrap();
+        let mut packet = packet.unwrap();
 
         // 3. Copy data to packet.
-        let mut string_to_set = String::from("Hello world");
-        string_to_set.push(' ');
-        string_to_set.push(current_letter);
+        write!(packet, "Hello world {}\0", current_letter)
+            .expect("string does not fit into packet");
         current_letter = (current_letter as u8 + 1) as char;
-        string_to_set.push('\0');
-        packet_mut.set_data(string_to_set.as_bytes());
 
         // 4. Send data.
-        csp_send(&mut conn.0, packet_mut);
+        csp_send(&mut conn.0, packet);
     }
 }
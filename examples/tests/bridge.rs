@@ -0,0 +1,91 @@
+//! Forwards packets between two Rust interfaces with a bridge.
+//!
+//! Packets received on one interface are transmitted on the other one, independently of their
+//! destination. The router is not running, because the bridge takes the packets from the
+//! incoming queue of the router.
+use std::{
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use libcsp::{
+    bridge::{Bridge, BridgeStats},
+    driver::{self, CspInterfaceDriver},
+    CspConfig, CspError, CspId, CspStack, ErrorCode, HeaderFlags, MsgPriority, Packet,
+};
+
+/// Driver which passes the transmitted packets to the test.
+struct Channel(Mutex<Sender<(CspId, Vec<u8>)>>);
+
+impl CspInterfaceDriver for Channel {
+    fn transmit(&self, _via: u16, packet: Packet, _from_me: bool) -> Result<(), CspError> {
+        self.0
+            .lock()
+            .unwrap()
+            .send((packet.id(), packet.to_vec()))
+            .map_err(|_| CspError::new("transmit", ErrorCode::Driver))
+    }
+}
+
+fn channel() -> (Channel, Receiver<(CspId, Vec<u8>)>) {
+    let (tx, rx) = mpsc::channel();
+    (Channel(Mutex::new(tx)), rx)
+}
+
+fn id(src: u16, dst: u16) -> CspId {
+    CspId {
+        prio: MsgPriority::Normal,
+        flags: HeaderFlags::empty(),
+        src,
+        dst,
+        dport: 10,
+        sport: 20,
+    }
+}
+
+#[test]
+fn bridge_forwards_both_directions() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    let (driver_a, rx_a) = channel();
+    let (driver_b, rx_b) = channel();
+    let if_a = driver::register(&csp, "A", 1, 8, false, driver_a).unwrap();
+    let if_b = driver::register(&csp, "B", 2, 8, false, driver_b).unwrap();
+
+    let bridge = Bridge::start(&csp, if_a.iface(), if_b.iface()).expect("starting bridge failed");
+    assert_eq!(
+        Bridge::start(&csp, if_a.iface(), if_b.iface())
+            .unwrap_err()
+            .code(),
+        ErrorCode::Busy
+    );
+
+    // The destinations are not on the subnets of the interfaces, the bridge forwards anyway.
+    if_a.receive_data(id(300, 400), b"from A").unwrap();
+    let (received_id, data) = rx_b
+        .recv_timeout(Duration::from_secs(1))
+        .expect("packet was not bridged to B");
+    assert_eq!(received_id, id(300, 400));
+    assert_eq!(data, b"from A");
+
+    if_b.receive_data(id(400, 300), b"from B").unwrap();
+    let (received_id, data) = rx_a
+        .recv_timeout(Duration::from_secs(1))
+        .expect("packet was not bridged to A");
+    assert_eq!(received_id, id(400, 300));
+    assert_eq!(data, b"from B");
+
+    assert_eq!(
+        bridge.stop(),
+        BridgeStats {
+            a_to_b: 1,
+            b_to_a: 1,
+            tx_error: 0,
+        }
+    );
+    // Another bridge can be started after stopping the first one.
+    let bridge = Bridge::start(&csp, if_b.iface(), if_a.iface()).expect("restarting failed");
+    assert_eq!(bridge.stats(), BridgeStats::default());
+}
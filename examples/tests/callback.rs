@@ -0,0 +1,76 @@
+//! Delivers datagrams over the loopback interface to port callbacks.
+use std::{
+    sync::mpsc::{self, Sender},
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use libcsp::{
+    callback, ConnectOpts, CspConfig, CspStack, DatagramSocket, ErrorCode, MsgPriority,
+    SocketFlags, CSP_LOOPBACK,
+};
+
+const PANIC_PORT: u8 = 18;
+const PORT: u8 = 19;
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+fn send(csp: &CspStack, socket: &DatagramSocket, dport: u8, data: &[u8]) {
+    let mut packet = csp.buffer_get().expect("no free buffer");
+    packet.set_data(data).unwrap();
+    socket.send_to(
+        MsgPriority::Normal,
+        CSP_LOOPBACK,
+        dport,
+        0,
+        ConnectOpts::NONE,
+        packet,
+    );
+}
+
+fn forward(tx: Sender<Vec<u8>>) -> impl Fn(libcsp::Packet) + Send + Sync {
+    let tx = Mutex::new(tx);
+    move |packet| tx.lock().unwrap().send(packet.to_vec()).unwrap()
+}
+
+#[test]
+fn callbacks_survive_panics_and_unregister() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+    let socket = DatagramSocket::new(SocketFlags::NONE);
+
+    callback::register(&csp, PANIC_PORT, |_| panic!("callback panicked"))
+        .expect("registering callback failed");
+    let (tx, rx) = mpsc::channel();
+    callback::register(&csp, PORT, forward(tx)).expect("registering callback failed");
+    assert_eq!(
+        callback::register(&csp, PORT, drop).unwrap_err().code(),
+        ErrorCode::Used
+    );
+    assert_eq!(
+        callback::register(&csp, callback::MAX_PORT + 1, drop)
+            .unwrap_err()
+            .code(),
+        ErrorCode::Inval
+    );
+
+    // The router keeps running after a callback panicked.
+    send(&csp, &socket, PANIC_PORT, b"panic");
+    send(&csp, &socket, PORT, b"first");
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), b"first");
+
+    // Packets for a port without a callback are dropped, but the port stays bound and a new
+    // callback can be registered.
+    assert!(callback::unregister(PORT));
+    assert!(!callback::is_registered(PORT));
+    send(&csp, &socket, PORT, b"dropped");
+    thread::sleep(Duration::from_millis(100));
+    let (tx, rx) = mpsc::channel();
+    callback::register(&csp, PORT, forward(tx)).expect("registering callback again failed");
+    send(&csp, &socket, PORT, b"second");
+    assert_eq!(rx.recv_timeout(TIMEOUT).unwrap(), b"second");
+}
@@ -0,0 +1,119 @@
+//! Connects two CSP nodes with CAN interfaces on a virtual SocketCAN device.
+//!
+//! The test requires a `vcan0` device and is skipped if it does not exist. It can be created
+//! with:
+//!
+//! ```sh
+//! sudo ip link add dev vcan0 type vcan
+//! sudo ip link set up vcan0
+//! ```
+//!
+//! The `libcsp` stack is a process-wide singleton, so the peer node runs in a child process,
+//! which executes the [can_peer] test of this binary.
+//!
+//! Requires the `can` feature.
+#![cfg(feature = "can")]
+use std::{
+    env,
+    path::Path,
+    process::{Child, Command},
+    thread,
+    time::Duration,
+};
+
+use libcsp::{
+    callback, can::CanInterface, csp_service_handler, CspConfig, CspStack, ErrorCode, SocketFlags,
+    CSP_ANY,
+};
+
+const DEVICE: &str = "vcan0";
+const PEER_ENV: &str = "CAN_PEER";
+const NODE_ADDR: u16 = 1;
+const PEER_ADDR: u16 = 2;
+const NETMASK: u16 = 8;
+
+fn vcan_available() -> bool {
+    let available = Path::new("/sys/class/net").join(DEVICE).exists();
+    if !available {
+        println!("skipping test, {} does not exist", DEVICE);
+    }
+    available
+}
+
+fn init_stack() -> CspStack {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+    csp
+}
+
+fn spawn_peer() -> Child {
+    Command::new(env::current_exe().unwrap())
+        .args(["can_peer", "--exact", "--nocapture"])
+        .env(PEER_ENV, "1")
+        .spawn()
+        .expect("spawning peer node failed")
+}
+
+/// Peer node which answers the standard services until it is killed. Only runs in the child
+/// process spawned by [can_ping_over_vcan].
+#[test]
+fn can_peer() {
+    if env::var(PEER_ENV).is_err() {
+        return;
+    }
+    let csp = init_stack();
+    CanInterface::builder(DEVICE)
+        .addr(PEER_ADDR)
+        .netmask(NETMASK)
+        .default_iface(true)
+        .build(&csp)
+        .expect("adding CAN interface failed");
+    callback::register(&csp, CSP_ANY, csp_service_handler).expect("binding services failed");
+    loop {
+        thread::park();
+    }
+}
+
+#[test]
+fn can_ping_over_vcan() {
+    if env::var(PEER_ENV).is_ok() {
+        return;
+    }
+    let csp = init_stack();
+    // A failed open must not leave an interface behind.
+    let result = CanInterface::builder("nocan0").name("NOCAN").build(&csp);
+    assert!(result.is_err());
+    assert!(libcsp::iflist::get_by_name(&csp, c"NOCAN").is_none());
+    if !vcan_available() {
+        return;
+    }
+
+    let can = CanInterface::builder(DEVICE)
+        .addr(NODE_ADDR)
+        .netmask(NETMASK)
+        .default_iface(true)
+        .build(&csp)
+        .expect("adding CAN interface failed");
+    let mut peer = spawn_peer();
+
+    // The peer needs some time to start up. The ping payload requires several CFP frames.
+    let result = (0..20).find_map(|_| {
+        csp.ping(PEER_ADDR, Duration::from_millis(250), 64, SocketFlags::NONE)
+            .ok()
+    });
+    peer.kill().unwrap();
+    peer.wait().unwrap();
+
+    assert!(result.is_some(), "no ping reply from the peer node");
+    let stats = can.stats();
+    assert!(stats.tx > 0);
+    assert!(stats.rx > 0);
+    assert_eq!(can.iface().name(), "CAN");
+    assert_eq!(can.iface().addr(), NODE_ADDR);
+    assert_eq!(can.iface().netmask(), NETMASK);
+    assert!(can.iface().is_default());
+}
@@ -0,0 +1,64 @@
+//! Inspects the connection table while a loopback connection is open.
+use std::{thread, time::Duration};
+
+use libcsp::{
+    conn, ConnType, ConnectOpts, CspConfig, CspStack, ErrorCode, MsgPriority, RdpState, Socket,
+    SocketFlags, CSP_LOOPBACK,
+};
+
+const PORT: u8 = 14;
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[test]
+fn conn_table_snapshots() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+    assert_eq!(conn::iter(&csp).len(), libcsp::ffi::CSP_CONN_MAX);
+    assert_eq!(conn::iter(&csp).filter(|info| info.is_open()).count(), 0);
+
+    let mut socket = Socket::new(SocketFlags::NONE);
+    socket.bind(&csp, PORT).expect("binding socket failed");
+    socket.listen(1).expect("listening failed");
+    let server = thread::spawn(move || {
+        let conn = socket.accept(TIMEOUT).expect("no connection");
+        // Keep the connection open until the client has inspected the table.
+        thread::sleep(Duration::from_millis(500));
+        drop(conn);
+    });
+
+    let client = csp
+        .connect_guarded(
+            MsgPriority::Normal,
+            CSP_LOOPBACK,
+            PORT,
+            TIMEOUT,
+            ConnectOpts::RDP,
+        )
+        .expect("connecting failed");
+    // The server connection is opened when the handshake arrives.
+    thread::sleep(Duration::from_millis(100));
+
+    let open: Vec<_> = conn::iter(&csp).filter(|info| info.is_open()).collect();
+    let outgoing = open
+        .iter()
+        .find(|info| info.kind == ConnType::Client)
+        .expect("no client connection");
+    assert_eq!(outgoing.idout.dst, CSP_LOOPBACK);
+    assert_eq!(outgoing.idout.dport, PORT);
+    assert_eq!(outgoing.sport_outgoing, outgoing.idout.sport);
+    assert!(outgoing.opts.contains(ConnectOpts::RDP));
+    assert_eq!(outgoing.rdp_state, Some(RdpState::Open));
+    let incoming = open
+        .iter()
+        .find(|info| info.kind == ConnType::Server)
+        .expect("no server connection");
+    assert_eq!(incoming.idin.dport, PORT);
+
+    assert!(!conn::table_string(&csp).is_empty());
+    drop(client);
+    server.join().unwrap();
+}
@@ -0,0 +1,68 @@
+//! Exchanges datagrams between two connection-less sockets over the loopback interface.
+use std::{thread, time::Duration};
+
+use libcsp::{
+    ConnectOpts, CspConfig, CspEndpoint, CspStack, DatagramSocket, ErrorCode, MsgPriority,
+    SocketFlags, CSP_LOOPBACK,
+};
+
+const SERVER_PORT: u8 = 16;
+const CLIENT_PORT: u8 = 17;
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[test]
+fn datagram_request_and_reply() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+
+    let mut server = DatagramSocket::new(SocketFlags::SAME);
+    assert!(server
+        .opts()
+        .contains(SocketFlags::CONN_LESS | SocketFlags::SAME));
+    server
+        .bind(&csp, SERVER_PORT)
+        .expect("binding server failed");
+    let mut client = DatagramSocket::new(SocketFlags::NONE);
+    client
+        .bind(&csp, CLIENT_PORT)
+        .expect("binding client failed");
+
+    assert_eq!(
+        server.recv_from(Duration::ZERO).unwrap_err().code(),
+        ErrorCode::TimedOut
+    );
+
+    let mut request = csp.buffer_get().expect("no free buffer");
+    request.set_data(b"request").unwrap();
+    client.send_to(
+        MsgPriority::High,
+        CSP_LOOPBACK,
+        SERVER_PORT,
+        CLIENT_PORT,
+        ConnectOpts::NONE,
+        request,
+    );
+    let (request, src) = server.recv_from(TIMEOUT).expect("no request received");
+    assert_eq!(&*request, b"request");
+    assert_eq!(request.id().prio, MsgPriority::High);
+    assert_eq!(request.id().dport, SERVER_PORT);
+    assert_eq!(
+        src,
+        CspEndpoint {
+            addr: CSP_LOOPBACK,
+            port: CLIENT_PORT,
+        }
+    );
+
+    let mut reply = csp.buffer_get().expect("no free buffer");
+    reply.set_data(b"reply").unwrap();
+    server.reply(&request, reply);
+    let (reply, src) = client.recv_from(TIMEOUT).expect("no reply received");
+    assert_eq!(&*reply, b"reply");
+    assert_eq!(src.port, SERVER_PORT);
+    assert_eq!(reply.id().dport, CLIENT_PORT);
+}
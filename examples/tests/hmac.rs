@@ -0,0 +1,71 @@
+//! Sends an authenticated packet over a loopback connection and verifies the sniffed copy in
+//! Rust.
+use std::{thread, time::Duration};
+
+use libcsp::{
+    csp_read, csp_send,
+    hmac::{self, HmacKey, HMAC_LENGTH},
+    promisc::Sniffer,
+    ConnectOpts, CspConfig, CspStack, ErrorCode, HeaderFlags, MsgPriority, Socket, SocketFlags,
+    CSP_LOOPBACK,
+};
+
+const PORT: u8 = 15;
+const TIMEOUT: Duration = Duration::from_secs(2);
+const KEY: &[u8] = b"integration test key";
+
+#[test]
+fn hmac_loopback() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    assert!(hmac::key(&csp).is_none());
+    hmac::set_key(&csp, b"previous key").expect("setting HMAC key failed");
+    hmac::set_key(&csp, KEY).expect("setting HMAC key failed");
+    let key = hmac::key(&csp).expect("no HMAC key");
+    assert_eq!(key.tag(b"hmac"), HmacKey::new(KEY).tag(b"hmac"));
+
+    let sniffer = Sniffer::new(&csp, 10).expect("enabling promiscuous mode failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+
+    let mut socket = Socket::new(SocketFlags::HMACREQ);
+    socket.bind(&csp, PORT).expect("binding socket failed");
+    socket.listen(1).expect("listening failed");
+    let server = thread::spawn(move || {
+        let mut conn = socket.accept(TIMEOUT).expect("no connection");
+        let packet = csp_read(conn.as_mut(), TIMEOUT).expect("no packet");
+        // The router removes the HMAC after verifying it.
+        assert_eq!(&*packet, b"hmac");
+    });
+
+    let mut conn = csp
+        .connect_guarded(
+            MsgPriority::Normal,
+            CSP_LOOPBACK,
+            PORT,
+            TIMEOUT,
+            ConnectOpts::HMAC,
+        )
+        .expect("connecting failed");
+    let mut packet = csp.buffer_get().expect("no free buffer");
+    packet.set_data(b"hmac").unwrap();
+    csp_send(conn.as_mut(), packet);
+    server.join().unwrap();
+
+    // The sniffed copy is taken before the router verifies the HMAC.
+    let mut sniffed = sniffer.read(TIMEOUT).expect("no sniffed packet");
+    assert!(sniffed.id().flags.contains(HeaderFlags::HMAC));
+    assert_eq!(sniffed.len(), b"hmac".len() + HMAC_LENGTH);
+    assert_eq!(
+        HmacKey::new(b"previous key")
+            .verify(&sniffed)
+            .unwrap_err()
+            .code(),
+        ErrorCode::Hmac
+    );
+    key.verify_packet(&mut sniffed)
+        .expect("HMAC verification failed");
+    assert_eq!(&*sniffed, b"hmac");
+}
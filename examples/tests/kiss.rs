@@ -0,0 +1,121 @@
+//! Connects two CSP nodes with KISS interfaces through a pseudo-terminal pair.
+//!
+//! The `libcsp` stack is a process-wide singleton, so the peer node runs in a child process,
+//! which executes the [kiss_peer] test of this binary.
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    os::fd::{FromRawFd, OwnedFd},
+    process::{Child, Command},
+    thread,
+    time::Duration,
+};
+
+use libcsp::{
+    callback, csp_service_handler, kiss::KissInterface, CspConfig, CspStack, ErrorCode,
+    SocketFlags, CSP_ANY,
+};
+
+const PEER_TTY_ENV: &str = "KISS_PEER_TTY";
+const NODE_ADDR: u16 = 1;
+const PEER_ADDR: u16 = 2;
+const NETMASK: u16 = 8;
+
+fn init_stack() -> CspStack {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+    csp
+}
+
+/// Open a pseudo-terminal pair in raw mode. Returns the master and the path of the slave.
+fn open_pty() -> (File, OwnedFd, String) {
+    let mut master = 0;
+    let mut slave = 0;
+    let mut name = [0; 64];
+    // SAFETY: All fields of the structure are plain integers.
+    let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+    // SAFETY: FFI calls with valid pointers. The name buffer is large enough for pty names.
+    unsafe {
+        libc::cfmakeraw(&mut termios);
+        assert_eq!(
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                name.as_mut_ptr(),
+                &termios,
+                std::ptr::null()
+            ),
+            0,
+            "openpty failed"
+        );
+    }
+    let name = name.iter().take_while(|&&c| c != 0).map(|&c| c as u8);
+    // SAFETY: openpty returned two open file descriptors which are owned by us.
+    unsafe {
+        (
+            File::from_raw_fd(master),
+            OwnedFd::from_raw_fd(slave),
+            String::from_utf8(name.collect()).unwrap(),
+        )
+    }
+}
+
+fn spawn_peer(tty: &str) -> Child {
+    Command::new(env::current_exe().unwrap())
+        .args(["kiss_peer", "--exact", "--nocapture"])
+        .env(PEER_TTY_ENV, tty)
+        .spawn()
+        .expect("spawning peer node failed")
+}
+
+/// Peer node which answers the standard services until the pseudo-terminal is closed. Only
+/// runs in the child process spawned by [kiss_ping_over_pty].
+#[test]
+fn kiss_peer() {
+    let Ok(tty) = env::var(PEER_TTY_ENV) else {
+        return;
+    };
+    let csp = init_stack();
+    let tty = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(tty)
+        .expect("opening pty failed");
+    let kiss = KissInterface::new(&csp, "KISS", PEER_ADDR, NETMASK, true, tty)
+        .expect("adding KISS interface failed");
+    callback::register(&csp, CSP_ANY, csp_service_handler).expect("binding services failed");
+    // Ends with an error when the master side is closed.
+    let _ = kiss.join();
+}
+
+#[test]
+fn kiss_ping_over_pty() {
+    if env::var(PEER_TTY_ENV).is_ok() {
+        return;
+    }
+    let (master, _slave, tty) = open_pty();
+    let csp = init_stack();
+    let kiss = KissInterface::new(&csp, "KISS", NODE_ADDR, NETMASK, true, master)
+        .expect("adding KISS interface failed");
+    let mut peer = spawn_peer(&tty);
+
+    // The peer needs some time to start up.
+    let result = (0..20).find_map(|_| {
+        csp.ping(PEER_ADDR, Duration::from_millis(250), 16, SocketFlags::NONE)
+            .ok()
+    });
+    peer.kill().unwrap();
+    peer.wait().unwrap();
+
+    assert!(result.is_some(), "no ping reply from the peer node");
+    let stats = kiss.iface().stats();
+    assert!(stats.tx > 0);
+    assert!(stats.rx > 0);
+    assert!(stats.txbytes > 0);
+    assert_eq!(kiss.iface().name(), "KISS");
+    assert_eq!(kiss.iface().addr(), NODE_ADDR);
+}
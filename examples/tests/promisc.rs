@@ -0,0 +1,63 @@
+//! Sniffs packets routed over the loopback interface in promiscuous mode.
+use std::{thread, time::Duration};
+
+use libcsp::{
+    promisc::Sniffer, ConnectOpts, CspConfig, CspStack, DatagramSocket, ErrorCode, MsgPriority,
+    SocketFlags, CSP_LOOPBACK,
+};
+
+const PORT: u8 = 20;
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+#[test]
+fn sniffer_copies_routed_packets() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+
+    let sniffer = Sniffer::new(&csp, 10).expect("enabling promiscuous mode failed");
+    assert_eq!(Sniffer::new(&csp, 10).unwrap_err().code(), ErrorCode::Busy);
+    assert_eq!(
+        sniffer.read(Duration::ZERO).unwrap_err().code(),
+        ErrorCode::TimedOut
+    );
+
+    let mut socket = DatagramSocket::new(SocketFlags::NONE);
+    socket.bind(&csp, PORT).expect("binding socket failed");
+    for data in [&b"first"[..], b"second"] {
+        let mut packet = csp.buffer_get().expect("no free buffer");
+        packet.set_data(data).unwrap();
+        socket.send_to(
+            MsgPriority::Normal,
+            CSP_LOOPBACK,
+            PORT,
+            PORT,
+            ConnectOpts::NONE,
+            packet,
+        );
+    }
+
+    // The packets are still delivered to the socket.
+    for data in [&b"first"[..], b"second"] {
+        let (packet, _) = socket.recv_from(TIMEOUT).expect("packet was not delivered");
+        assert_eq!(&*packet, data);
+    }
+    let sniffed: Vec<_> = sniffer.packets(TIMEOUT).take(2).collect();
+    assert_eq!(sniffed.len(), 2);
+    assert_eq!(&*sniffed[0], b"first");
+    assert_eq!(&*sniffed[1], b"second");
+    assert!(sniffed.iter().all(|packet| packet.id().dport == PORT));
+    // The iteration ends when no packet arrives in time.
+    assert_eq!(sniffer.packets(Duration::from_millis(10)).count(), 0);
+
+    // A new sniffer can be created after the first one was dropped.
+    drop(sniffer);
+    let sniffer = Sniffer::new(&csp, 10).expect("enabling promiscuous mode again failed");
+    assert_eq!(
+        sniffer.read(Duration::ZERO).unwrap_err().code(),
+        ErrorCode::TimedOut
+    );
+}
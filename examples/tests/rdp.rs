@@ -0,0 +1,90 @@
+//! Configures the RDP options and inspects the RDP state of a loopback connection.
+use std::{thread, time::Duration};
+
+use libcsp::{
+    csp_read, csp_send,
+    rdp::{self, RdpOptions},
+    ConnectOpts, CspConfig, CspStack, ErrorCode, MsgPriority, RdpState, Socket, SocketFlags,
+    CSP_LOOPBACK,
+};
+
+const PORT: u8 = 13;
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[test]
+fn rdp_options_and_conn_state() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+
+    let options = RdpOptions {
+        window_size: 3,
+        conn_timeout: Duration::from_secs(5),
+        packet_timeout: Duration::from_millis(500),
+        delayed_acks: false,
+        ack_timeout: Duration::from_millis(100),
+        ack_delay_count: 1,
+    };
+    rdp::set_options(&csp, &options).expect("setting RDP options failed");
+    assert_eq!(rdp::options(&csp), options);
+    assert_eq!(
+        rdp::set_options(
+            &csp,
+            &RdpOptions {
+                window_size: 0,
+                ..options
+            }
+        )
+        .unwrap_err()
+        .code(),
+        ErrorCode::Inval
+    );
+
+    let mut socket = Socket::new(SocketFlags::NONE);
+    socket.bind(&csp, PORT).expect("binding socket failed");
+    socket.listen(1).expect("listening failed");
+    let server = thread::spawn(move || {
+        let mut conn = socket.accept(TIMEOUT).expect("no connection");
+        let packet = csp_read(conn.as_mut(), TIMEOUT).expect("no packet");
+        let state = rdp::conn_state(conn.as_ref()).expect("not an RDP connection");
+        drop(packet);
+        state
+    });
+
+    let mut conn = csp
+        .connect_guarded(
+            MsgPriority::Normal,
+            CSP_LOOPBACK,
+            PORT,
+            TIMEOUT,
+            ConnectOpts::RDP,
+        )
+        .expect("connecting failed");
+    let state = rdp::conn_state(conn.as_ref()).expect("not an RDP connection");
+    assert_eq!(state.state, RdpState::Open);
+    assert_eq!(state.options, options);
+    assert_eq!(state.unacked(), 0);
+
+    let mut packet = csp.buffer_get().expect("no free buffer");
+    packet.set_data(b"rdp").unwrap();
+    csp_send(conn.as_mut(), packet);
+    let server_state = server.join().unwrap();
+    assert_eq!(server_state.state, RdpState::Open);
+    // The client transmits its options to the server.
+    assert_eq!(server_state.options.window_size, options.window_size);
+    assert_eq!(server_state.options.packet_timeout, options.packet_timeout);
+
+    let udp_conn = csp
+        .connect_guarded(
+            MsgPriority::Normal,
+            CSP_LOOPBACK,
+            PORT,
+            TIMEOUT,
+            ConnectOpts::NONE,
+        )
+        .expect("connecting failed");
+    assert!(rdp::conn_state(udp_conn.as_ref()).is_none());
+}
@@ -0,0 +1,72 @@
+//! Transfers blobs which are much larger than a packet with SFP over the loopback interface.
+//!
+//! The server receives a blob into a vector and sends it back, and the client receives the
+//! echo into a buffer. A second transfer is rejected by the server because it is too large.
+//! RDP connections are used, so the sender can not exhaust the buffer pool.
+use std::{thread, time::Duration};
+
+use libcsp::{
+    sfp::{self, sfp_recv, sfp_recv_into, sfp_recv_max, sfp_send},
+    ConnectOpts, CspConfig, CspStack, ErrorCode, MsgPriority, Socket, SocketFlags, CSP_LOOPBACK,
+};
+
+const PORT: u8 = 12;
+const BLOB_SIZE: usize = 8 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[test]
+fn sfp_echo_over_loopback() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+
+    let mut socket = Socket::new(SocketFlags::NONE);
+    socket.bind(&csp, PORT).expect("binding socket failed");
+    socket.listen(1).expect("listening failed");
+    let server = thread::spawn(move || {
+        let mut conn = socket.accept(TIMEOUT).expect("no connection");
+        let mtu = sfp::max_mtu(conn.as_mut());
+        assert_eq!(mtu, sfp::MAX_MTU - sfp::RDP_HEADER_SIZE);
+        let blob = sfp_recv(conn.as_mut(), TIMEOUT).expect("receiving blob failed");
+        sfp_send(conn.as_mut(), &blob, mtu, TIMEOUT).expect("sending echo failed");
+        assert_eq!(
+            sfp_recv_max(conn.as_mut(), 2 * mtu, TIMEOUT)
+                .unwrap_err()
+                .code(),
+            ErrorCode::Sfp
+        );
+        blob
+    });
+
+    let blob: Vec<u8> = (0..BLOB_SIZE).map(|i| (i * 7 % 251) as u8).collect();
+    let mut conn = csp
+        .connect_guarded(
+            MsgPriority::Normal,
+            CSP_LOOPBACK,
+            PORT,
+            TIMEOUT,
+            ConnectOpts::RDP,
+        )
+        .expect("connecting failed");
+    assert_eq!(
+        sfp_send(conn.as_mut(), &blob, sfp::MAX_MTU, TIMEOUT)
+            .unwrap_err()
+            .code(),
+        ErrorCode::Inval
+    );
+    let mtu = sfp::max_mtu(conn.as_mut());
+    sfp_send(conn.as_mut(), &blob, mtu, TIMEOUT).expect("sending blob failed");
+
+    let mut echo = vec![0; BLOB_SIZE + 100];
+    let len = sfp_recv_into(conn.as_mut(), &mut echo, TIMEOUT).expect("receiving echo failed");
+    assert_eq!(len, BLOB_SIZE);
+    assert_eq!(&echo[..len], &blob[..]);
+
+    // The server only accepts two chunks. The send may fail when the server closes the
+    // connection, so its result is not checked.
+    let _ = sfp_send(conn.as_mut(), &blob[..2 * mtu + 1], mtu, TIMEOUT);
+    assert_eq!(server.join().unwrap(), blob);
+}
@@ -0,0 +1,34 @@
+//! Binds sockets and checks that their ports are released when they are closed or dropped.
+use libcsp::{CspConfig, CspSocket, CspStack, ErrorCode, Socket, SocketFlags};
+
+const PORT: u8 = 15;
+
+#[test]
+fn bind_and_release_ports() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+
+    let mut socket = Socket::new(SocketFlags::NONE);
+    assert_eq!(socket.port(), None);
+    socket.bind(&csp, PORT).expect("binding socket failed");
+    assert_eq!(socket.port(), Some(PORT));
+    assert_eq!(
+        socket.bind(&csp, PORT + 1).unwrap_err().code(),
+        ErrorCode::Already
+    );
+
+    let mut other = Socket::new(SocketFlags::NONE);
+    assert_eq!(other.bind(&csp, PORT).unwrap_err().code(), ErrorCode::Used);
+    assert_eq!(other.port(), None);
+
+    drop(socket);
+    other
+        .bind(&csp, PORT)
+        .expect("port was not released when the socket was dropped");
+    other.close().expect("closing socket failed");
+
+    let storage: &'static mut CspSocket = Box::leak(Box::default());
+    let mut socket = Socket::new_static(storage, SocketFlags::NONE);
+    socket
+        .bind(&csp, PORT)
+        .expect("port was not released when the socket was closed");
+}
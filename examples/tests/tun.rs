@@ -0,0 +1,92 @@
+//! Sends a packet through an encrypted TUN interface which tunnels to the own node.
+//!
+//! The TUN interface has the address of the receiver and the tunnel endpoint in its subnet, so
+//! the packet is encrypted, routed back to the TUN interface as a tunneled packet, decrypted
+//! and finally delivered locally.
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use libcsp::{
+    crypto::{self, AeadProvider, CryptoProvider},
+    tun::{TunConfig, TunInterface},
+    ConnectOpts, CspConfig, CspError, CspStack, DatagramSocket, ErrorCode, MsgPriority,
+    SocketFlags,
+};
+
+const TUN_ADDR: u16 = 20;
+const TUN_ENDPOINT: u16 = 100;
+// The top 7 bits of both addresses match.
+const NETMASK: u16 = 7;
+const PORT: u8 = 10;
+
+/// Provider which counts the calls of the wrapped provider.
+struct Counting {
+    provider: AeadProvider,
+    encrypted: Arc<AtomicU32>,
+    decrypted: Arc<AtomicU32>,
+}
+
+impl CryptoProvider for Counting {
+    fn encrypt(&self, msg: &[u8], ciphertext: &mut [u8]) -> Result<usize, CspError> {
+        self.encrypted.fetch_add(1, Ordering::Relaxed);
+        self.provider.encrypt(msg, ciphertext)
+    }
+
+    fn decrypt(&self, ciphertext: &[u8], msg: &mut [u8]) -> Result<usize, CspError> {
+        self.decrypted.fetch_add(1, Ordering::Relaxed);
+        self.provider.decrypt(ciphertext, msg)
+    }
+}
+
+#[test]
+fn tun_loopback() {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+
+    let encrypted = Arc::new(AtomicU32::new(0));
+    let decrypted = Arc::new(AtomicU32::new(0));
+    crypto::install(Counting {
+        provider: AeadProvider::new(&[0x42; crypto::KEY_LEN], 1),
+        encrypted: encrypted.clone(),
+        decrypted: decrypted.clone(),
+    });
+    let config = TunConfig {
+        src: TUN_ENDPOINT,
+        dst: TUN_ENDPOINT,
+    };
+    let tun = TunInterface::new(&csp, "TUN", TUN_ADDR, NETMASK, false, config)
+        .expect("adding TUN interface failed");
+
+    let mut socket = DatagramSocket::new(SocketFlags::NONE);
+    socket.bind(&csp, PORT).expect("binding socket failed");
+    let mut packet = csp.buffer_get().expect("no free buffer");
+    packet.set_data(b"tunneled payload").unwrap();
+    socket.send_to(
+        MsgPriority::Normal,
+        TUN_ADDR,
+        PORT,
+        PORT,
+        ConnectOpts::NONE,
+        packet,
+    );
+
+    let (packet, src) = socket
+        .recv_from(Duration::from_secs(1))
+        .expect("tunneled packet was not received");
+    assert_eq!(&*packet, b"tunneled payload");
+    assert_eq!(src.port, PORT);
+    assert_eq!(encrypted.load(Ordering::Relaxed), 1);
+    assert_eq!(decrypted.load(Ordering::Relaxed), 1);
+    assert!(tun.iface().stats().tx >= 2);
+    crypto::uninstall();
+}
@@ -0,0 +1,139 @@
+//! Connects two CSP nodes with ZMQ hub interfaces through a locally spawned proxy.
+//!
+//! The proxy forwards the packets published by the interfaces to their subscriptions, like the
+//! `zmqproxy` of `libcsp`. The `libcsp` stack is a process-wide singleton, so the peer node runs
+//! in a child process, which executes the [zmq_peer] test of this binary.
+//!
+//! Requires the `zmq` feature and an installed libzmq.
+#![cfg(feature = "zmq")]
+use std::{
+    env,
+    ffi::{c_char, c_int, c_void, CString},
+    process::{Child, Command},
+    thread,
+    time::Duration,
+};
+
+use libcsp::{
+    callback, csp_service_handler, zmq::ZmqInterface, CspConfig, CspStack, ErrorCode, SocketFlags,
+    CSP_ANY,
+};
+
+const PEER_ENV: &str = "ZMQ_PEER";
+// Not the default ports, so the test does not interfere with a running proxy.
+const SUBSCRIBE_PORT: u16 = 16000;
+const PUBLISH_PORT: u16 = 17000;
+const NODE_ADDR: u16 = 1;
+const PEER_ADDR: u16 = 2;
+const NETMASK: u16 = 8;
+
+const ZMQ_XPUB: c_int = 9;
+const ZMQ_XSUB: c_int = 10;
+
+#[link(name = "zmq")]
+extern "C" {
+    fn zmq_ctx_new() -> *mut c_void;
+    fn zmq_socket(context: *mut c_void, kind: c_int) -> *mut c_void;
+    fn zmq_bind(socket: *mut c_void, endpoint: *const c_char) -> c_int;
+    fn zmq_proxy(frontend: *mut c_void, backend: *mut c_void, capture: *mut c_void) -> c_int;
+}
+
+/// Run a proxy which forwards the packets published to [SUBSCRIBE_PORT] to the subscribers on
+/// [PUBLISH_PORT] until the process exits.
+fn spawn_proxy() {
+    fn bind(context: *mut c_void, kind: c_int, port: u16) -> *mut c_void {
+        let endpoint = CString::new(libcsp::zmq::tcp_endpoint("127.0.0.1", port)).unwrap();
+        // SAFETY: FFI calls with a valid context and endpoint.
+        unsafe {
+            let socket = zmq_socket(context, kind);
+            assert!(!socket.is_null(), "creating proxy socket failed");
+            assert_eq!(
+                zmq_bind(socket, endpoint.as_ptr()),
+                0,
+                "binding proxy failed"
+            );
+            socket
+        }
+    }
+
+    thread::spawn(|| {
+        // SAFETY: FFI call.
+        let context = unsafe { zmq_ctx_new() };
+        assert!(!context.is_null(), "creating ZMQ context failed");
+        let frontend = bind(context, ZMQ_XSUB, SUBSCRIBE_PORT);
+        let backend = bind(context, ZMQ_XPUB, PUBLISH_PORT);
+        // SAFETY: FFI call with valid sockets. Only returns when the context is terminated.
+        unsafe { zmq_proxy(frontend, backend, std::ptr::null_mut()) };
+    });
+}
+
+fn init_stack() -> CspStack {
+    let csp = CspStack::init(&CspConfig::default()).expect("initializing CSP stack failed");
+    thread::spawn(move || loop {
+        if let Err(e) = csp.route_work() {
+            assert_eq!(e.code(), ErrorCode::TimedOut, "CSP router error: {}", e);
+        }
+    });
+    csp
+}
+
+fn add_interface(csp: &CspStack, addr: u16) -> ZmqInterface {
+    ZmqInterface::builder()
+        .publish_endpoint(libcsp::zmq::tcp_endpoint("127.0.0.1", SUBSCRIBE_PORT))
+        .subscribe_endpoint(libcsp::zmq::tcp_endpoint("127.0.0.1", PUBLISH_PORT))
+        .addr(addr)
+        .netmask(NETMASK)
+        .default_iface(true)
+        .build(csp)
+        .expect("adding ZMQ interface failed")
+}
+
+fn spawn_peer() -> Child {
+    Command::new(env::current_exe().unwrap())
+        .args(["zmq_peer", "--exact", "--nocapture"])
+        .env(PEER_ENV, "1")
+        .spawn()
+        .expect("spawning peer node failed")
+}
+
+/// Peer node which answers the standard services until it is killed. Only runs in the child
+/// process spawned by [zmq_ping_over_proxy].
+#[test]
+fn zmq_peer() {
+    if env::var(PEER_ENV).is_err() {
+        return;
+    }
+    let csp = init_stack();
+    add_interface(&csp, PEER_ADDR);
+    callback::register(&csp, CSP_ANY, csp_service_handler).expect("binding services failed");
+    loop {
+        thread::park();
+    }
+}
+
+#[test]
+fn zmq_ping_over_proxy() {
+    if env::var(PEER_ENV).is_ok() {
+        return;
+    }
+    spawn_proxy();
+    let csp = init_stack();
+    let zmq = add_interface(&csp, NODE_ADDR);
+    let mut peer = spawn_peer();
+
+    // The peer needs some time to start up and to subscribe.
+    let result = (0..20).find_map(|_| {
+        csp.ping(PEER_ADDR, Duration::from_millis(250), 16, SocketFlags::NONE)
+            .ok()
+    });
+    peer.kill().unwrap();
+    peer.wait().unwrap();
+
+    assert!(result.is_some(), "no ping reply from the peer node");
+    let stats = zmq.stats();
+    assert!(stats.tx > 0);
+    assert!(stats.rx > 0);
+    assert_eq!(zmq.iface().name(), "ZMQHUB");
+    assert_eq!(zmq.iface().netmask(), NETMASK);
+    assert!(!zmq.promisc());
+}
@@ -0,0 +1,4 @@
+// Only required so that the unit tests can write generated files into `OUT_DIR`.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+}
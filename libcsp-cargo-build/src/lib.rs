@@ -51,6 +51,7 @@ const SRCS_LIST: &[&str] = &[
     "csp_dedup.c",
     "csp_services.c",
     "csp_service_handler.c",
+    "csp_sfp.c",
     "interfaces/csp_if_lo.c",
     "interfaces/csp_if_kiss.c",
     "interfaces/csp_if_tun.c",
@@ -59,6 +60,8 @@ const SRCS_LIST: &[&str] = &[
     "crypto/csp_sha1.c",
 ];
 
+const CAN_SRCS: &[&str] = &["interfaces/csp_if_can.c", "interfaces/csp_if_can_pbuf.c"];
+
 const ARCH_SRCS_UNIX: &[&str] = &[
     "arch/posix/csp_clock.c",
     "arch/posix/csp_semaphore.c",
@@ -86,6 +89,16 @@ pub struct Config {
     pub promisc: bool,
     pub rdp: bool,
     pub yaml: bool,
+    /// Compile the CAN interface with the CAN fragmentation protocol (CFP). On Linux, the
+    /// SocketCAN driver is compiled as well.
+    pub can: bool,
+    /// Use `libsocketcan` to configure the bitrate of SocketCAN devices. The library is linked
+    /// dynamically and must be installed. It is only linked if the SocketCAN driver is compiled,
+    /// which requires [Config::can] on Linux.
+    pub libsocketcan: bool,
+    /// Compile the ZeroMQ hub interface, which sets `CSP_HAVE_LIBZMQ` and links `libzmq`. The
+    /// library is linked dynamically and must be installed.
+    pub zmq: bool,
 }
 
 impl Default for Config {
@@ -108,6 +121,9 @@ impl Default for Config {
             promisc: true,
             rdp: true,
             yaml: false,
+            can: false,
+            libsocketcan: false,
+            zmq: false,
         }
     }
 }
@@ -218,6 +234,28 @@ impl Builder {
             next_file.push("csp_rtable_cidr.c");
             self.build.file(next_file);
         }
+        if self.cfg.can {
+            for src in CAN_SRCS {
+                let mut next_file = self.libcsp_src_path_base.clone();
+                next_file.push(src);
+                self.build.file(next_file);
+            }
+            #[cfg(target_os = "linux")]
+            {
+                let mut next_file = self.libcsp_src_path_base.clone();
+                next_file.push("drivers/can/can_socketcan.c");
+                self.build.file(next_file);
+                if self.cfg.libsocketcan {
+                    println!("cargo:rustc-link-lib=socketcan");
+                }
+            }
+        }
+        if self.cfg.zmq {
+            let mut next_file = self.libcsp_src_path_base.clone();
+            next_file.push("interfaces/csp_if_zmqhub.c");
+            self.build.file(next_file);
+            println!("cargo:rustc-link-lib=zmq");
+        }
 
         // TODO: UNIX does not necesarilly mean POSIX? Details to deal with later..
         #[cfg(unix)]
@@ -364,9 +402,16 @@ pub fn generate_autoconf_header_file(out_dir: impl AsRef<Path>, cfg: &Config) ->
     ));
 
     autoconf_file_string.push('\n');
-    // TODO: Maybe those will be added at some point.. For now, they are hardcoded to 0.
-    autoconf_file_string.push_str(&format!("#define {} {}\n", cfg_keys::HAVE_LIBSOCKETCAN, 0));
-    autoconf_file_string.push_str(&format!("#define {} {}\n", cfg_keys::HAVE_LIBZMQ, 0));
+    autoconf_file_string.push_str(&format!(
+        "#define {} {}\n",
+        cfg_keys::HAVE_LIBSOCKETCAN,
+        cfg.libsocketcan as u32
+    ));
+    autoconf_file_string.push_str(&format!(
+        "#define {} {}\n",
+        cfg_keys::HAVE_LIBZMQ,
+        cfg.zmq as u32
+    ));
     let out_file = out_dir.join("autoconfig.h");
     let mut file = std::fs::File::create(out_file)?;
     file.write_all(autoconf_file_string.as_bytes())?;
@@ -427,5 +472,30 @@ pub fn generate_autoconf_rust_file(out_dir: impl AsRef<Path>, cfg: &Config) -> i
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     // TODO: Unittest autoconf generators.
+
+    #[test]
+    fn autoconf_header_optional_libraries() {
+        type Enable = fn(&mut Config);
+        let cases: [(&str, Enable); 2] = [
+            (cfg_keys::HAVE_LIBSOCKETCAN, |cfg| cfg.libsocketcan = true),
+            (cfg_keys::HAVE_LIBZMQ, |cfg| cfg.zmq = true),
+        ];
+        let out_dir = Path::new(env!("OUT_DIR")).join("test-libraries");
+        std::fs::create_dir_all(&out_dir).unwrap();
+        for (key, enable) in cases {
+            let mut cfg = Config::default();
+            generate_autoconf_header_file(&out_dir, &cfg).unwrap();
+            let header = std::fs::read_to_string(out_dir.join("autoconfig.h")).unwrap();
+            assert!(header.contains(&format!("#define {key} 0\n")), "{key}");
+
+            enable(&mut cfg);
+            generate_autoconf_header_file(&out_dir, &cfg).unwrap();
+            let header = std::fs::read_to_string(out_dir.join("autoconfig.h")).unwrap();
+            assert!(header.contains(&format!("#define {key} 1\n")), "{key}");
+        }
+        std::fs::remove_dir_all(&out_dir).unwrap();
+    }
 }
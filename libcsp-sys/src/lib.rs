@@ -27,6 +27,11 @@ pub mod config;
 // possible without these constants.
 include!(concat!(env!("OUT_DIR"), "/autoconfig.rs"));
 
+pub const CSP_REBOOT_MAGIC: u32 = 0x80078007;
+pub const CSP_REBOOT_SHUTDOWN_MAGIC: u32 = 0xD1E5529A;
+pub const CSP_IFLIST_NAME_MAX: u32 = 10;
+pub const CSP_NO_VIA_ADDRESS: u32 = 65535;
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct csp_timestamp_t {
@@ -172,6 +177,17 @@ pub type csp_conn_t = csp_conn_s;
 
 pub type atomic_int = u32;
 
+pub type csp_callback_t = ::core::option::Option<unsafe extern "C" fn(packet: *mut csp_packet_t)>;
+
+#[doc = " Memory pointer"]
+pub type csp_memptr_t = *mut ::core::ffi::c_void;
+#[doc = " Const memory pointer"]
+pub type csp_const_memptr_t = *const ::core::ffi::c_void;
+#[doc = " Platform specific memory copy function."]
+pub type csp_memcpy_fnc_t = ::core::option::Option<
+    unsafe extern "C" fn(arg1: csp_memptr_t, arg2: csp_const_memptr_t, arg3: usize) -> csp_memptr_t,
+>;
+
 #[doc = " Connection states"]
 pub type csp_conn_state_t = ::core::ffi::c_uint;
 
@@ -227,7 +243,7 @@ pub struct csp_conn_s {
     pub rx_queue_static: csp_static_queue_t,
     pub rx_queue_static_data:
         [core::ffi::c_char; CSP_CONN_RXQUEUE_LEN * core::mem::size_of::<*const csp_packet_s>()],
-    pub callback: ::core::option::Option<unsafe extern "C" fn(packet: *mut csp_packet_t)>,
+    pub callback: csp_callback_t,
     pub dest_socket: *mut csp_socket_t,
     pub timestamp: u32,
     pub opts: u32,
@@ -344,6 +360,55 @@ impl Default for csp_if_udp_conf_t {
     }
 }
 
+#[doc = "< Deduplication off"]
+pub const csp_dedup_types_CSP_DEDUP_OFF: csp_dedup_types = 0;
+#[doc = "< Deduplication on forwarding only"]
+pub const csp_dedup_types_CSP_DEDUP_FWD: csp_dedup_types = 1;
+#[doc = "< Deduplication on incomfing only"]
+pub const csp_dedup_types_CSP_DEDUP_INCOMING: csp_dedup_types = 2;
+#[doc = "< Deduplication on incoming and forwarding"]
+pub const csp_dedup_types_CSP_DEDUP_ALL: csp_dedup_types = 3;
+#[doc = " CSP Debug Types"]
+pub type csp_dedup_types = ::core::ffi::c_uint;
+
+#[doc = " CSP configuration."]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct csp_conf_s {
+    #[doc = "< Protocol version to use (either 1 or 2)"]
+    pub version: u8,
+    #[doc = "< Host name, returned by the #CSP_CMP_IDENT request"]
+    pub hostname: *const ::core::ffi::c_char,
+    #[doc = "< Model, returned by the #CSP_CMP_IDENT request"]
+    pub model: *const ::core::ffi::c_char,
+    #[doc = "< Revision, returned by the #CSP_CMP_IDENT request"]
+    pub revision: *const ::core::ffi::c_char,
+    #[doc = "< Default connection options. Options will always be or'ed onto new connections, see csp_connect()"]
+    pub conn_dfl_so: u32,
+    #[doc = "< Enable CSP deduplication. 0 = off, 1 = always on, 2 = only on forwarded packets,"]
+    pub dedup: u8,
+}
+
+#[doc = " CSP configuration."]
+pub type csp_conf_t = csp_conf_s;
+
+#[doc = " Routing table entry."]
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct csp_route_s {
+    pub address: u16,
+    pub netmask: u16,
+    pub via: u16,
+    pub iface: *mut csp_iface_t,
+}
+
+pub type csp_route_t = csp_route_s;
+
+#[doc = " Iterator for looping through the routing table."]
+pub type csp_rtable_iterator_t = ::core::option::Option<
+    unsafe extern "C" fn(ctx: *mut ::core::ffi::c_void, route: *mut csp_route_t) -> bool,
+>;
+
 extern "C" {
     #[doc = " Error counters"]
     pub static mut csp_dbg_buffer_out: u8;
@@ -357,9 +422,15 @@ extern "C" {
     pub static mut csp_dbg_rdp_print: u8;
     pub static mut csp_dbg_packet_print: u8;
 
+    #[doc = " CSP configuration. Must be set before calling csp_init()."]
+    pub static mut csp_conf: csp_conf_t;
+
     #[doc = " Initialize CSP.\n This will configure basic structures."]
     pub fn csp_init();
 
+    #[doc = " Get a \\a read-only reference to the active CSP configuration.\n\n @return Active CSP configuration (read-only)."]
+    pub fn csp_get_conf() -> *const csp_conf_t;
+
     pub fn csp_print_func(fmt: *const core::ffi::c_char, ...);
 
     #[doc = " Bind port to socket.\n\n @param[in] socket socket to bind port to\n @param[in] port port number to bind, use #CSP_ANY for all ports. Bindnig to a specific will take precedence over #CSP_ANY.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
@@ -371,6 +442,32 @@ extern "C" {
     #[doc = " Route packet from the incoming router queue and check RDP timeouts.\n In order for incoming packets to routed and RDP timeouts to be checked, this function must be called reguarly.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
     pub fn csp_route_work() -> ::core::ffi::c_int;
 
+    #[doc = " Set the bridge interfaces.\n\n @param[in] if_a CSP Interface `A`\n @param[in] if_b CSP Interface `B`"]
+    pub fn csp_bridge_set_interfaces(if_a: *mut csp_iface_t, if_b: *mut csp_iface_t);
+
+    #[doc = " Bridge packet from an interface to the other."]
+    pub fn csp_bridge_work();
+
+    #[doc = " Set RDP options.\n The RDP options are used from the connecting/client side. When a RDP connection\n is established, the client tranmits the options to the server.\n\n @param[in] window_size window size\n @param[in] conn_timeout_ms connection timeout in mS\n @param[in] packet_timeout_ms packet timeout in mS.\n @param[in] delayed_acks enable/disable delayed acknowledgements.\n @param[in] ack_timeout acknowledgement timeout when delayed ACKs is enabled\n @param[in] ack_delay_count send acknowledgement for every ack_delay_count packets.\n"]
+    pub fn csp_rdp_set_opt(
+        window_size: ::core::ffi::c_uint,
+        conn_timeout_ms: ::core::ffi::c_uint,
+        packet_timeout_ms: ::core::ffi::c_uint,
+        delayed_acks: ::core::ffi::c_uint,
+        ack_timeout: ::core::ffi::c_uint,
+        ack_delay_count: ::core::ffi::c_uint,
+    );
+
+    #[doc = " Get RDP options. @see csp_rdp_set_opt()\n\n @param[out] window_size Window size\n @param[out] conn_timeout_ms connection timeout in ms\n @param[out] packet_timeout_ms packet timeout in ms\n @param[out] delayed_acks enable/disable delayed acknowledgements\n @param[out] ack_timeout acknowledgement timeout when delayed ACKs is enabled\n @param[out] ack_delay_count send acknowledgement for every ack_delay_count packets"]
+    pub fn csp_rdp_get_opt(
+        window_size: *mut ::core::ffi::c_uint,
+        conn_timeout_ms: *mut ::core::ffi::c_uint,
+        packet_timeout_ms: *mut ::core::ffi::c_uint,
+        delayed_acks: *mut ::core::ffi::c_uint,
+        ack_timeout: *mut ::core::ffi::c_uint,
+        ack_delay_count: *mut ::core::ffi::c_uint,
+    );
+
     #[doc = " Wait/accept a new connection.\n\n @param[in] socket socket to accept connections on, created by calling csp_socket().\n @param[in] timeout  timeout in mS to wait for a connection, use CSP_MAX_TIMEOUT for infinite timeout.\n @return New connection on success, NULL on failure or timeout."]
     pub fn csp_accept(socket: *mut csp_socket_t, timeout: u32) -> *mut csp_conn_t;
 
@@ -380,9 +477,38 @@ extern "C" {
     #[doc = " Send packet on a connection.\n The packet buffer is automatically freed, and cannot be used after the call to csp_send()\n\n @param[in] conn connection\n @param[in] packet packet to send"]
     pub fn csp_send(conn: *mut csp_conn_t, packet: *mut csp_packet_t);
 
+    #[doc = " Send data over a CSP connection.\n\n Data will be send in chunks of \\a mtu bytes. The MTU must be small enough to fit\ninto a CSP packat + SFP header + other transport headers.\n\n csp_sfp_recv() or csp_sfp_recv_fp() can be used at the other end to receive data.\n\n This is usefull if you wish to send data stored in flash memory or another location, where standard memcpy() doesn't work.\n\n @param[in] conn established connection for sending SFP packets.\n @param[in] data data to send\n @param[in] datasize  tsize of \\a data\n @param[in] mtu  maximum transfer unit (bytes), max data chunk to send.\n @param[in] timeout unused as of CSP version 1.6\n @param[in] memcpyfcn memory copy function.\n @return #CSP_ERR_NONE on success, otherwise an error."]
+    pub fn csp_sfp_send_own_memcpy(
+        conn: *mut csp_conn_t,
+        data: *const ::core::ffi::c_void,
+        datasize: ::core::ffi::c_uint,
+        mtu: ::core::ffi::c_uint,
+        timeout: u32,
+        memcpyfcn: csp_memcpy_fnc_t,
+    ) -> ::core::ffi::c_int;
+
+    #[doc = " Receive data over a CSP connection.\n\n This is the counterpart to the csp_sfp_send() and csp_sfp_send_own_memcpy().\n\n @param[in] conn established connection for receiving SFP packets.\n @param[out] dataout received data on success. Allocated with malloc(), so\n \t\t\t   should be freed with free(). The pointer will be NULL on failure.\n @param[out] datasize size of received data.\n @param[in] timeout timeout in ms to wait for csp_read()\n @param[in] first_packet First packet of a SFP transfer.\n \t\t\t  Use NULL to receive first packet on the connection.\n @return #CSP_ERR_NONE on success, otherwise an error."]
+    pub fn csp_sfp_recv_fp(
+        conn: *mut csp_conn_t,
+        dataout: *mut *mut ::core::ffi::c_void,
+        datasize: *mut ::core::ffi::c_int,
+        timeout: u32,
+        first_packet: *mut csp_packet_t,
+    ) -> ::core::ffi::c_int;
+
     #[doc = " Change the default priority of the connection and send a packet.\n\n .. note:: The priority of the connection will be changed.\n           If you need to change it back, call csp_send_prio() again.\n\n @param[in] prio priority to set on the connection\n @param[in] conn connection\n @param[in] packet packet to send"]
     pub fn csp_send_prio(prio: u8, conn: *mut csp_conn_t, packet: *mut csp_packet_t);
 
+    #[doc = " Send a packet without previously opening a connection.\n\n @param[in] prio priority, see #csp_prio_t\n @param[in] dst destination address\n @param[in] dst_port destination port\n @param[in] src_port source port\n @param[in] opts connection options, see @ref CSP_CONNECTION_OPTIONS.\n @param[in] packet packet to send"]
+    pub fn csp_sendto(
+        prio: u8,
+        dst: u16,
+        dst_port: u8,
+        src_port: u8,
+        opts: u32,
+        packet: *mut csp_packet_t,
+    );
+
     #[doc = " Send a packet as a reply to a request (without a connection).\n Calls csp_sendto() with the source address and port from the request.\n\n @param[in] request incoming request\n @param[out] reply reply packet\n @param[in] opts connection options, see @ref CSP_CONNECTION_OPTIONS."]
     pub fn csp_sendto_reply(request: *const csp_packet_t, reply: *mut csp_packet_t, opts: u32);
 
@@ -468,6 +594,9 @@ extern "C" {
     #[doc = " Close a socket, freeing it's RX queue and unbinding it from the associated\n port.\n\n @param[in] sock Socket\n @return #CSP_ERR_NONE on success, otherwise an error code."]
     pub fn csp_socket_close(sock: *mut csp_socket_t) -> ::core::ffi::c_int;
 
+    #[doc = " Bind port to callback function.\n\n @param[in] callback pointer to callback function\n @param[in] port port number to bind, use #CSP_ANY for all ports. Bindnig to a specific will take precedence over #CSP_ANY.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+    pub fn csp_bind_callback(callback: csp_callback_t, port: u8) -> ::core::ffi::c_int;
+
     #[doc = " Return destination port of connection.\n\n @param[in] conn connection\n @return destination port of an incoming connection"]
     pub fn csp_conn_dport(conn: *const csp_conn_t) -> ::core::ffi::c_int;
     #[doc = " Return source port of connection.\n\n @param[in] conn connection\n @return source port of an incoming connection"]
@@ -487,6 +616,22 @@ extern "C" {
 
     #[doc = " Print connection table to stdout."]
     pub fn csp_conn_print_table();
+
+    #[doc = " Print connection table to string."]
+    pub fn csp_conn_print_table_str(
+        str_buf: *mut ::core::ffi::c_char,
+        str_size: ::core::ffi::c_int,
+    ) -> ::core::ffi::c_int;
+
+    #[doc = " Get the connection array.\n\n @param[out] size number of connections in the array, which is #CSP_CONN_MAX.\n @return The connection array."]
+    pub fn csp_conn_get_array(size: *mut usize) -> *const csp_conn_t;
+
+    #[doc = " Send packet to the router input queue.\n\n This is the function interface drivers use to pass received packets to the router.\n\n @param[in] packet CSP packet, the ownership is passed to the router.\n @param[in] iface incoming interface.\n @param[out] pxTaskWoken must be NULL if called from task context."]
+    pub fn csp_qfifo_write(
+        packet: *mut csp_packet_t,
+        iface: *mut csp_iface_t,
+        pxTaskWoken: *mut ::core::ffi::c_void,
+    );
 }
 
 pub mod iflist {
@@ -498,6 +643,206 @@ pub mod iflist {
 
         pub fn csp_iflist_print();
 
+        #[doc = " Remove interface from the list.\n\n @param[in] ifc Interface to remove. NULL will be gracefully handled."]
+        pub fn csp_iflist_remove(ifc: *mut csp_iface_t);
+
+        pub fn csp_iflist_get_by_name(name: *const ::core::ffi::c_char) -> *mut csp_iface_t;
+
+        pub fn csp_iflist_get_by_addr(addr: u16) -> *mut csp_iface_t;
+
+        pub fn csp_iflist_get_by_subnet(addr: u16, from: *mut csp_iface_t) -> *mut csp_iface_t;
+
+        pub fn csp_iflist_get_by_index(idx: ::core::ffi::c_int) -> *mut csp_iface_t;
+
+        pub fn csp_iflist_get() -> *mut csp_iface_t;
+
+    }
+}
+
+pub mod promisc {
+    use super::*;
+
+    extern "C" {
+        #[doc = " Enable promiscuous packet queue.\n\n @param[in]queue_size: Size (max length) of queue for incoming packets.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+        pub fn csp_promisc_enable(queue_size: ::core::ffi::c_uint) -> ::core::ffi::c_int;
+
+        #[doc = " Disable promiscuous mode."]
+        pub fn csp_promisc_disable();
+
+        #[doc = " Get/dequeue packet from promiscuous packet queue.\n\n Returns the first packet from the promiscuous packet queue.\n\n @param[in] timeout Timeout in ms to wait for a packet.\n @return Packet (free with csp_buffer_free() or re-use packet), NULL on error or timeout."]
+        pub fn csp_promisc_read(timeout: u32) -> *mut csp_packet_t;
+    }
+}
+
+pub mod rtable {
+    use super::*;
+
+    extern "C" {
+        pub fn csp_rtable_find_route(dest_address: u16) -> *mut csp_route_t;
+
+        #[doc = " Set route to destination address/node.\n\n @param[in] dest_address destination address.\n @param[in]mask number of bits in netmask (set to -1 for maximum number of bits)\n @param[in] ifc interface.\n @param[in] via assosicated via address.\n @return #CSP_ERR_NONE on success, or an error code."]
+        pub fn csp_rtable_set(
+            dest_address: u16,
+            netmask: ::core::ffi::c_int,
+            ifc: *mut csp_iface_t,
+            via: u16,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Save routing table as a string (readable format).\n @see csp_rtable_load() for additional information, e.g. format.\n\n @param[out] buffer user supplied buffer.\n @param[in] buffer_size size of \\a buffer.\n @return #CSP_ERR_NONE on success, or an error code."]
+        pub fn csp_rtable_save(
+            buffer: *mut ::core::ffi::c_char,
+            buffer_size: usize,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Load routing table from a string.\n Table will be loaded on-top of existing routes, possibly overwriting existing entries.\n Format: \\<address\\>[/mask] \\<interface\\> [via][, next entry]\n Example: \"0/0 CAN, 8 KISS, 10 I2C 10\", same as \"0/0 CAN, 8/5 KISS, 10/5 I2C 10\".\n @see csp_rtable_save(), csp_rtable_clear(), csp_rtable_free()\n\n @param[in] rtable routing table (nul terminated)\n @return CSP_ERR or number of entries."]
+        pub fn csp_rtable_load(rtable: *const ::core::ffi::c_char) -> ::core::ffi::c_int;
+
+        #[doc = " Clear routing table and add loopback route.\n @see csp_rtable_free()"]
+        pub fn csp_rtable_clear();
+
+        #[doc = " Clear/free all entries in the routing table."]
+        pub fn csp_rtable_free();
+
+        #[doc = " Iterate routing table."]
+        pub fn csp_rtable_iterate(iter: csp_rtable_iterator_t, ctx: *mut ::core::ffi::c_void);
+
+        #[doc = " Print routing table"]
+        pub fn csp_rtable_print();
+    }
+}
+
+pub mod can {
+    use super::*;
+
+    pub const CSP_IF_CAN_DEFAULT_NAME: &[u8; 4] = b"CAN\0";
+
+    extern "C" {
+        #[doc = " Open CAN socket and add CSP interface.\n\n @param[in] device CAN device name (Linux device).\n @param[in] ifname CSP interface name, use #CSP_IF_CAN_DEFAULT_NAME for default name.\n @param[in] node_id CSP address of the interface.\n @param[in] bitrate if different from 0, it will be attempted to change the bitrate on the CAN device - this may require increased OS privileges.\n @param[in] promisc if \\a true, receive all CAN frames. If \\a false a filter is set on the CAN device, using device->addr\n @param[out] return_iface the added interface.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+        pub fn csp_can_socketcan_open_and_add_interface(
+            device: *const ::core::ffi::c_char,
+            ifname: *const ::core::ffi::c_char,
+            node_id: ::core::ffi::c_uint,
+            bitrate: ::core::ffi::c_int,
+            promisc: bool,
+            return_iface: *mut *mut csp_iface_t,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Stop the Rx thread and free resources (testing).\n\n @note This will invalidate CSP, because an interface can't be removed. This is primarily for testing.\n\n @param[in] iface interface to stop.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+        pub fn csp_can_socketcan_stop(iface: *mut csp_iface_t) -> ::core::ffi::c_int;
+    }
+}
+
+pub mod hmac {
+    use super::*;
+
+    #[doc = " Number of bytes from the HMAC calculation, that is appended to the CSP message."]
+    pub const CSP_HMAC_LENGTH: u32 = 4;
+
+    extern "C" {
+        #[doc = " Append HMAC to packet\n\n @param[in] packet Pointer to packet\n @param[in] include_header use header in hmac calculation (this will not modify the flags field)\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+        pub fn csp_hmac_append(
+            packet: *mut csp_packet_t,
+            include_header: bool,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Verify HMAC of packet\n\n @param[in] packet Pointer to packet\n @param[in] include_header use header in hmac calculation (this will not modify the flags field)\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+        pub fn csp_hmac_verify(
+            packet: *mut csp_packet_t,
+            include_header: bool,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Calculate HMAC on buffer\n\n This function is used by append/verify but cal also be called separately.\n @param[in] key HMAC key\n @param[in] keylen HMAC key length\n @param[in] data pointer to data\n @param[in] datalen lehgth of data\n @param[out] hmac calculated HMAC hash, minimum #CSP_SHA1_DIGESTSIZE.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+        pub fn csp_hmac_memory(
+            key: *const ::core::ffi::c_void,
+            keylen: u32,
+            data: *const ::core::ffi::c_void,
+            datalen: u32,
+            hmac: *mut u8,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Save a copy of the key string for use by the append/verify functions\n\n @param[in] key HMAC key\n @param[in] keylen HMAC key length\n @return Always returns #CSP_ERR_NONE"]
+        pub fn csp_hmac_set_key(key: *const ::core::ffi::c_void, keylen: u32)
+            -> ::core::ffi::c_int;
+    }
+}
+
+pub mod kiss {
+    use super::*;
+
+    #[doc = " KISS interface transmit function.\n\n @param[in] driver_data driver data from #csp_iface_t\n @param[in] data data to transmit\n @param[in] len length of \\a data.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+    pub type csp_kiss_driver_tx_f = ::core::option::Option<
+        unsafe extern "C" fn(
+            driver_data: *mut ::core::ffi::c_void,
+            data: *const u8,
+            len: usize,
+        ) -> ::core::ffi::c_int,
+    >;
+
+    pub const csp_kiss_mode_e_KISS_MODE_NOT_STARTED: csp_kiss_mode_e = 0;
+    pub const csp_kiss_mode_e_KISS_MODE_STARTED: csp_kiss_mode_e = 1;
+    pub const csp_kiss_mode_e_KISS_MODE_ESCAPED: csp_kiss_mode_e = 2;
+    pub const csp_kiss_mode_e_KISS_MODE_SKIP_FRAME: csp_kiss_mode_e = 3;
+    #[doc = " KISS Rx mode/state."]
+    pub type csp_kiss_mode_e = ::core::ffi::c_uint;
+
+    #[doc = " KISS interface data (state information)."]
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone)]
+    pub struct csp_kiss_interface_data_t {
+        #[doc = " Tx function"]
+        pub tx_func: csp_kiss_driver_tx_f,
+        #[doc = " Rx mode/state."]
+        pub rx_mode: csp_kiss_mode_e,
+        #[doc = " Rx length"]
+        pub rx_length: ::core::ffi::c_uint,
+        #[doc = " Rx first - if set, waiting for first character (== TNC_DATA) after start"]
+        pub rx_first: bool,
+        #[doc = " CSP packet for storing Rx data."]
+        pub rx_packet: *mut csp_packet_t,
+    }
+
+    impl Default for csp_kiss_interface_data_t {
+        fn default() -> Self {
+            Self {
+                tx_func: None,
+                rx_mode: csp_kiss_mode_e_KISS_MODE_NOT_STARTED,
+                rx_length: 0,
+                rx_first: false,
+                rx_packet: core::ptr::null_mut(),
+            }
+        }
+    }
+
+    extern "C" {
+        #[doc = " Add interface.\n\n @param[in] iface CSP interface, initialized with name and inteface_data pointing to a valid #csp_kiss_interface_data_t structure.\n @return #CSP_ERR_NONE on success, otherwise an error code."]
+        pub fn csp_kiss_add_interface(iface: *mut csp_iface_t) -> ::core::ffi::c_int;
+
+        #[doc = " Process received KISS data.\n\n Called from driver, when data has been received.\n\n @param[in] iface incoming interface.\n @param[in] buf received data.\n @param[in] len length of \\a buf.\n @param[out] pxTaskWoken Valid reference if called from ISR, otherwise NULL!"]
+        pub fn csp_kiss_rx(
+            iface: *mut csp_iface_t,
+            buf: *const u8,
+            len: usize,
+            pxTaskWoken: *mut ::core::ffi::c_void,
+        );
+    }
+}
+
+pub mod tun {
+    use super::*;
+
+    #[doc = " TUN interface configuration."]
+    #[repr(C)]
+    #[derive(Debug, Default, Copy, Clone)]
+    pub struct csp_if_tun_conf_t {
+        #[doc = " Tunnel source address"]
+        pub tun_src: u16,
+        #[doc = " Tunnel destination address"]
+        pub tun_dst: u16,
+    }
+
+    extern "C" {
+        #[doc = " Setup TUN interface.\n\n Packets routed to the interface are encrypted with csp_crypto_encrypt() and sent to the\n tunnel destination. Tunneled packets addressed to the tunnel source are decrypted with\n csp_crypto_decrypt() and passed to the router.\n\n @param[in] iface CSP interface, initialized with name.\n @param[in] ifconf tunnel configuration, referenced by the interface."]
+        pub fn csp_if_tun_init(iface: *mut csp_iface_t, ifconf: *mut csp_if_tun_conf_t);
     }
 }
 
@@ -512,10 +857,59 @@ pub mod udp {
     }
 }
 
+pub mod zmqhub {
+    use super::*;
+
+    #[doc = " Default ZMQ proxy port for incoming packets, which the interfaces publish to."]
+    pub const CSP_ZMQPROXY_SUBSCRIBE_PORT: u32 = 6000;
+    #[doc = " Default ZMQ proxy port for outgoing packets, which the interfaces subscribe to."]
+    pub const CSP_ZMQPROXY_PUBLISH_PORT: u32 = 7000;
+    #[doc = " Default ZMQ hub interface name."]
+    pub const CSP_ZMQHUB_IF_NAME: &[u8; 7] = b"ZMQHUB\0";
+
+    extern "C" {
+        #[doc = " Format endpoint connection string for ZMQ.\n\n @param[in] host host name of IP.\n @param[in] port IP port.\n @param[out] buf user allocated buffer for receiving formatted string.\n @param[in] buf_size size of \\a buf.\n @return #CSP_ERR_NONE on success.\n @return #CSP_ERR_NOMEM if supplied buffer too small."]
+        pub fn csp_zmqhub_make_endpoint(
+            host: *const ::core::ffi::c_char,
+            port: u16,
+            buf: *mut ::core::ffi::c_char,
+            buf_size: usize,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Setup ZMQ interface.\n\n @param[in] addr only receive messages matching this address (255 means all). This is the CSP address of the interface.\n @param[in] host host name or IP of zmqproxy host. Endpoints are created using the \"default\" ports #CSP_ZMQPROXY_SUBSCRIBE_PORT and #CSP_ZMQPROXY_PUBLISH_PORT.\n @param[in] flags flags for controlling features on the connection.\n @param[out] return_interface created CSP interface.\n @return #CSP_ERR_NONE on succcess - else assert."]
+        pub fn csp_zmqhub_init(
+            addr: u16,
+            host: *const ::core::ffi::c_char,
+            flags: u32,
+            return_interface: *mut *mut csp_iface_t,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Setup ZMQ interface.\n\n @param[in] addr only receive messages matching this address (255 means all). This is the CSP address of the interface.\n @param[in] publish_endpoint publish (tx) endpoint -> connect to zmqproxy's subscribe port #CSP_ZMQPROXY_SUBSCRIBE_PORT.\n @param[in] subscribe_endpoint subscribe (rx) endpoint -> connect to zmqproxy's publish port #CSP_ZMQPROXY_PUBLISH_PORT.\n @param[in] flags flags for controlling features on the connection.\n @param[out] return_interface created CSP interface.\n @return #CSP_ERR_NONE on succcess - else assert."]
+        pub fn csp_zmqhub_init_w_endpoints(
+            addr: u16,
+            publish_endpoint: *const ::core::ffi::c_char,
+            subscribe_endpoint: *const ::core::ffi::c_char,
+            flags: u32,
+            return_interface: *mut *mut csp_iface_t,
+        ) -> ::core::ffi::c_int;
+
+        #[doc = " Setup ZMQ interface.\n\n @param[in] ifname Name of CSP interface, use NULL for default name #CSP_ZMQHUB_IF_NAME.\n @param[in] addr CSP address of the interface.\n @param[in] publish_endpoint publish (tx) endpoint -> connect to zmqproxy's subscribe port #CSP_ZMQPROXY_SUBSCRIBE_PORT.\n @param[in] subscribe_endpoint subscribe (rx) endpoint -> connect to zmqproxy's publish port #CSP_ZMQPROXY_PUBLISH_PORT.\n @param[in] promisc if non-zero, receive all packets. Otherwise only the packets addressed to \\a addr are received.\n @param[out] return_interface created CSP interface.\n @return #CSP_ERR_NONE on succcess - else assert."]
+        pub fn csp_zmqhub_init_w_name_endpoints_rxfilter(
+            ifname: *const ::core::ffi::c_char,
+            addr: u16,
+            publish_endpoint: *const ::core::ffi::c_char,
+            subscribe_endpoint: *const ::core::ffi::c_char,
+            promisc: ::core::ffi::c_int,
+            return_interface: *mut *mut csp_iface_t,
+        ) -> ::core::ffi::c_int;
+    }
+}
+
 /// Hook module for CSP.
 ///
 /// You can override these methods by providing them with an implementation block in your
-/// application for library with the function signature specified in this module.
+/// application for library with the function signature specified in this module. The `hooks`
+/// feature of the [`libcsp`](https://crates.io/crates/libcsp) crate defines all of them.
 pub mod hooks {
     use super::*;
 
@@ -1397,4 +1791,220 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn bindgen_test_layout_csp_conf_s() {
+        const UNINIT: MaybeUninit<csp_conf_s> = MaybeUninit::uninit();
+        let ptr = UNINIT.as_ptr();
+        assert_eq!(
+            size_of::<csp_conf_s>(),
+            40usize,
+            concat!("Size of: ", stringify!(csp_conf_s))
+        );
+        assert_eq!(
+            align_of::<csp_conf_s>(),
+            8usize,
+            concat!("Alignment of ", stringify!(csp_conf_s))
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).version) as usize - ptr as usize },
+            0usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_conf_s),
+                "::",
+                stringify!(version)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).hostname) as usize - ptr as usize },
+            8usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_conf_s),
+                "::",
+                stringify!(hostname)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).model) as usize - ptr as usize },
+            16usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_conf_s),
+                "::",
+                stringify!(model)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).revision) as usize - ptr as usize },
+            24usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_conf_s),
+                "::",
+                stringify!(revision)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).conn_dfl_so) as usize - ptr as usize },
+            32usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_conf_s),
+                "::",
+                stringify!(conn_dfl_so)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).dedup) as usize - ptr as usize },
+            36usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_conf_s),
+                "::",
+                stringify!(dedup)
+            )
+        );
+    }
+
+    #[test]
+    fn bindgen_test_layout_csp_kiss_interface_data_t() {
+        use super::kiss::csp_kiss_interface_data_t;
+        const UNINIT: MaybeUninit<csp_kiss_interface_data_t> = MaybeUninit::uninit();
+        let ptr = UNINIT.as_ptr();
+        assert_eq!(
+            size_of::<csp_kiss_interface_data_t>(),
+            32usize,
+            concat!("Size of: ", stringify!(csp_kiss_interface_data_t))
+        );
+        assert_eq!(
+            align_of::<csp_kiss_interface_data_t>(),
+            8usize,
+            concat!("Alignment of ", stringify!(csp_kiss_interface_data_t))
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).tx_func) as usize - ptr as usize },
+            0usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_kiss_interface_data_t),
+                "::",
+                stringify!(tx_func)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).rx_mode) as usize - ptr as usize },
+            8usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_kiss_interface_data_t),
+                "::",
+                stringify!(rx_mode)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).rx_length) as usize - ptr as usize },
+            12usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_kiss_interface_data_t),
+                "::",
+                stringify!(rx_length)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).rx_first) as usize - ptr as usize },
+            16usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_kiss_interface_data_t),
+                "::",
+                stringify!(rx_first)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).rx_packet) as usize - ptr as usize },
+            24usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_kiss_interface_data_t),
+                "::",
+                stringify!(rx_packet)
+            )
+        );
+    }
+
+    #[test]
+    fn bindgen_test_layout_csp_if_tun_conf_t() {
+        use super::tun::csp_if_tun_conf_t;
+        const UNINIT: MaybeUninit<csp_if_tun_conf_t> = MaybeUninit::uninit();
+        let ptr = UNINIT.as_ptr();
+        assert_eq!(
+            size_of::<csp_if_tun_conf_t>(),
+            4usize,
+            concat!("Size of: ", stringify!(csp_if_tun_conf_t))
+        );
+        assert_eq!(
+            align_of::<csp_if_tun_conf_t>(),
+            2usize,
+            concat!("Alignment of ", stringify!(csp_if_tun_conf_t))
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).tun_src) as usize - ptr as usize },
+            0usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_if_tun_conf_t),
+                "::",
+                stringify!(tun_src)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).tun_dst) as usize - ptr as usize },
+            2usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_if_tun_conf_t),
+                "::",
+                stringify!(tun_dst)
+            )
+        );
+    }
+
+    #[test]
+    fn bindgen_test_layout_csp_route_s() {
+        const UNINIT: MaybeUninit<csp_route_s> = MaybeUninit::uninit();
+        let ptr = UNINIT.as_ptr();
+        assert_eq!(
+            size_of::<csp_route_s>(),
+            16usize,
+            concat!("Size of: ", stringify!(csp_route_s))
+        );
+        assert_eq!(
+            align_of::<csp_route_s>(),
+            8usize,
+            concat!("Alignment of ", stringify!(csp_route_s))
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).via) as usize - ptr as usize },
+            4usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_route_s),
+                "::",
+                stringify!(via)
+            )
+        );
+        assert_eq!(
+            unsafe { core::ptr::addr_of!((*ptr).iface) as usize - ptr as usize },
+            8usize,
+            concat!(
+                "Offset of field: ",
+                stringify!(csp_route_s),
+                "::",
+                stringify!(iface)
+            )
+        );
+    }
 }
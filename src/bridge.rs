@@ -0,0 +1,216 @@
+//! Transparent bridge between two interfaces.
+//!
+//! A [Bridge] forwards every packet received on one interface to the other interface, without
+//! looking at its destination. This can be used to connect two networks, for example a KISS
+//! radio link and a UDP ground network, on a node which does not take part in the CSP network
+//! itself.
+//!
+//! The bridge takes the packets from the incoming queue of the router, so [CspStack::route_work]
+//! must not be called while a bridge is running.
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::{
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    ffi,
+    iflist::{IfaceRef, IfaceStats},
+    CspError, CspStack, ErrorCode,
+};
+
+static BRIDGE_ACTIVE: AtomicBool = AtomicBool::new(false);
+static COUNTERS: Counters = Counters::new();
+
+/// Number of packets forwarded by a [Bridge] since it was started.
+///
+/// The packets are counted with the transmit counters of the interfaces, so packets which other
+/// tasks send on the bridged interfaces while the bridge forwards a packet are counted as well.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct BridgeStats {
+    /// Packets forwarded from interface `A` to interface `B`.
+    pub a_to_b: u32,
+    /// Packets forwarded from interface `B` to interface `A`.
+    pub b_to_a: u32,
+    /// Packets which could not be transmitted on either interface.
+    pub tx_error: u32,
+}
+
+struct Counters {
+    a_to_b: AtomicU32,
+    b_to_a: AtomicU32,
+    tx_error: AtomicU32,
+}
+
+impl Counters {
+    const fn new() -> Self {
+        Self {
+            a_to_b: AtomicU32::new(0),
+            b_to_a: AtomicU32::new(0),
+            tx_error: AtomicU32::new(0),
+        }
+    }
+
+    /// Count the packets transmitted by one [ffi::csp_bridge_work] call from the difference of
+    /// the interface counters before and after the call.
+    fn add(&self, a: (IfaceStats, IfaceStats), b: (IfaceStats, IfaceStats)) {
+        let diff = |(before, after): (IfaceStats, IfaceStats)| {
+            (
+                after.tx.wrapping_sub(before.tx),
+                after.tx_error.wrapping_sub(before.tx_error),
+            )
+        };
+        let (a_tx, a_tx_error) = diff(a);
+        let (b_tx, b_tx_error) = diff(b);
+        self.a_to_b.fetch_add(b_tx, Ordering::Relaxed);
+        self.b_to_a.fetch_add(a_tx, Ordering::Relaxed);
+        self.tx_error
+            .fetch_add(a_tx_error.wrapping_add(b_tx_error), Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> BridgeStats {
+        BridgeStats {
+            a_to_b: self.a_to_b.load(Ordering::Relaxed),
+            b_to_a: self.b_to_a.load(Ordering::Relaxed),
+            tx_error: self.tx_error.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reset(&self) {
+        self.a_to_b.store(0, Ordering::Relaxed);
+        self.b_to_a.store(0, Ordering::Relaxed);
+        self.tx_error.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Handle of a running bridge. The bridge is stopped when the handle is dropped.
+///
+/// `libcsp` only supports a single pair of bridged interfaces, so only one bridge can exist at a
+/// time.
+#[derive(Debug)]
+pub struct Bridge {
+    if_a: IfaceRef,
+    if_b: IfaceRef,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    running: bool,
+}
+
+impl Bridge {
+    /// Rust wrapper for [ffi::csp_bridge_set_interfaces], which runs [ffi::csp_bridge_work] on a
+    /// dedicated thread until the bridge is stopped. [CspStack::route_work] must not be called
+    /// until the bridge is stopped.
+    ///
+    /// Returns [ErrorCode::Inval] if both interfaces are the same, [ErrorCode::Busy] if another
+    /// bridge exists and [ErrorCode::NoMem] if the thread could not be spawned.
+    pub fn start(_stack: &CspStack, if_a: IfaceRef, if_b: IfaceRef) -> Result<Self, CspError> {
+        if if_a.as_ptr() == if_b.as_ptr() {
+            return Err(CspError::new("csp_bridge_set_interfaces", ErrorCode::Inval));
+        }
+        if BRIDGE_ACTIVE.swap(true, Ordering::SeqCst) {
+            return Err(CspError::new("csp_bridge_set_interfaces", ErrorCode::Busy));
+        }
+        COUNTERS.reset();
+        // SAFETY: FFI call. Interfaces are never freed while they are in use.
+        unsafe { ffi::csp_bridge_set_interfaces(if_a.as_ptr(), if_b.as_ptr()) };
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name("csp-bridge".into())
+                .spawn(move || {
+                    while !stop.load(Ordering::Acquire) {
+                        let (a, b) = (if_a.stats(), if_b.stats());
+                        // SAFETY: FFI call. Returns after the timeout of the incoming queue if
+                        // there is no packet, so the stop flag is checked regularly.
+                        unsafe { ffi::csp_bridge_work() };
+                        COUNTERS.add((a, if_a.stats()), (b, if_b.stats()));
+                    }
+                })
+        };
+        let mut bridge = Self {
+            if_a,
+            if_b,
+            stop,
+            thread: None,
+            running: true,
+        };
+        match thread {
+            Ok(thread) => bridge.thread = Some(thread),
+            Err(_) => {
+                bridge.shutdown();
+                return Err(CspError::new("csp_bridge_work", ErrorCode::NoMem));
+            }
+        }
+        Ok(bridge)
+    }
+
+    /// Bridged interfaces `A` and `B`.
+    pub fn interfaces(&self) -> (IfaceRef, IfaceRef) {
+        (self.if_a, self.if_b)
+    }
+
+    /// Packets forwarded since the bridge was started.
+    pub fn stats(&self) -> BridgeStats {
+        COUNTERS.stats()
+    }
+
+    /// Stop the bridge and wait for its thread. Returns the final statistics.
+    pub fn stop(mut self) -> BridgeStats {
+        self.shutdown()
+    }
+
+    fn shutdown(&mut self) -> BridgeStats {
+        if !self.running {
+            return BridgeStats::default();
+        }
+        self.running = false;
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let stats = COUNTERS.stats();
+        BRIDGE_ACTIVE.store(false, Ordering::SeqCst);
+        stats
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters() {
+        let counters = Counters::new();
+        let stats = |tx, tx_error| IfaceStats {
+            tx,
+            tx_error,
+            ..Default::default()
+        };
+        // One packet from A to B.
+        counters.add((stats(5, 0), stats(5, 0)), (stats(7, 1), stats(8, 1)));
+        // One packet from B to A, which failed.
+        counters.add((stats(5, 0), stats(5, 1)), (stats(8, 1), stats(8, 1)));
+        // The counters of B wrapped around.
+        counters.add(
+            (stats(5, 1), stats(6, 1)),
+            (stats(u32::MAX, 1), stats(1, 2)),
+        );
+        assert_eq!(
+            counters.stats(),
+            BridgeStats {
+                a_to_b: 3,
+                b_to_a: 1,
+                tx_error: 2,
+            }
+        );
+        counters.reset();
+        assert_eq!(counters.stats(), BridgeStats::default());
+    }
+}
@@ -0,0 +1,210 @@
+//! Closure-based port callbacks on top of [ffi::csp_bind_callback].
+//!
+//! The router invokes a registered callback directly for every packet arriving on its port, so
+//! lightweight services do not need their own socket and task. All ports use the same C
+//! trampoline, which looks up the Rust closure for the destination port of the packet and hands
+//! over the packet as an owned [Packet]. Panics in the closure are caught at the FFI boundary and
+//! the packet is freed in that case.
+//!
+//! `libcsp` can not unbind a callback port. [unregister] therefore only removes the closure from
+//! the registry: the port stays bound to the trampoline, which drops packets for ports without a
+//! closure, and a new closure can be registered for the port later on.
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{ffi, CspError, CspStack, ErrorCode, Packet, CSP_ANY};
+
+/// Highest port which can be bound, apart from [CSP_ANY]. Configured with
+/// [ffi::CSP_PORT_MAX_BIND].
+pub const MAX_PORT: u8 = ffi::CSP_PORT_MAX_BIND as u8;
+
+const ANY_SLOT: usize = MAX_PORT as usize + 1;
+const NUM_SLOTS: usize = ANY_SLOT + 1;
+
+type Callback = Arc<dyn Fn(Packet) + Send + Sync>;
+
+struct Registry {
+    /// Ports which are bound to the trampoline on the C side.
+    bound: [bool; NUM_SLOTS],
+    callbacks: [Option<Callback>; NUM_SLOTS],
+}
+
+impl Registry {
+    const fn new() -> Self {
+        Self {
+            bound: [false; NUM_SLOTS],
+            callbacks: [const { None }; NUM_SLOTS],
+        }
+    }
+
+    /// Store the callback of a slot. `bind` is invoked to bind the port to the trampoline if it
+    /// was not bound before.
+    fn insert(
+        &mut self,
+        slot: usize,
+        callback: Callback,
+        bind: impl FnOnce() -> Result<(), CspError>,
+    ) -> Result<(), CspError> {
+        if self.callbacks[slot].is_some() {
+            return Err(CspError::new("csp_bind_callback", ErrorCode::Used));
+        }
+        if !self.bound[slot] {
+            bind()?;
+            self.bound[slot] = true;
+        }
+        self.callbacks[slot] = Some(callback);
+        Ok(())
+    }
+
+    /// Remove the callback of a slot. The port stays bound to the trampoline.
+    fn remove(&mut self, slot: usize) -> bool {
+        self.callbacks[slot].take().is_some()
+    }
+
+    /// Callback for a destination port, which is the [CSP_ANY] callback if the port has none.
+    fn lookup(&self, dport: u8) -> Option<Callback> {
+        let port_callback = if dport <= MAX_PORT {
+            self.callbacks[dport as usize].clone()
+        } else {
+            None
+        };
+        port_callback.or_else(|| self.callbacks[ANY_SLOT].clone())
+    }
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry::new());
+
+fn registry() -> MutexGuard<'static, Registry> {
+    // Callbacks are invoked without holding the lock, so a poisoned lock still contains a
+    // consistent registry.
+    REGISTRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn slot(port: u8) -> Result<usize, CspError> {
+    match port {
+        CSP_ANY => Ok(ANY_SLOT),
+        0..=MAX_PORT => Ok(port as usize),
+        _ => Err(CspError::new("csp_bind_callback", ErrorCode::Inval)),
+    }
+}
+
+unsafe extern "C" fn trampoline(packet: *mut ffi::csp_packet_t) {
+    // SAFETY: The router passes the ownership of the packet to the callback.
+    let Some(packet) = (unsafe { Packet::from_raw(packet) }) else {
+        return;
+    };
+    let callback = registry().lookup(packet.id().dport);
+    match callback {
+        Some(callback) => {
+            // The packet is dropped during unwinding if the callback panics.
+            let _ = catch_unwind(AssertUnwindSafe(|| callback(packet)));
+        }
+        None => drop(packet),
+    }
+}
+
+/// Register a callback for a port. Use [CSP_ANY] to receive the packets for all ports which are
+/// not bound otherwise. The callback is invoked in the context of the router task.
+///
+/// Returns [ErrorCode::Used] if the port already has a registered callback or is bound to a
+/// socket, and [ErrorCode::Inval] if the port is invalid.
+pub fn register(
+    _stack: &CspStack,
+    port: u8,
+    callback: impl Fn(Packet) + Send + Sync + 'static,
+) -> Result<(), CspError> {
+    let slot = slot(port)?;
+    registry().insert(slot, Arc::new(callback), || {
+        // SAFETY: FFI call. The trampoline is valid for the whole program lifetime.
+        let result = unsafe { ffi::csp_bind_callback(Some(trampoline), port) };
+        CspError::check("csp_bind_callback", result)
+    })
+}
+
+/// Remove the callback of a port. Returns [false] if no callback was registered.
+///
+/// A callback invocation which is already running is completed.
+pub fn unregister(port: u8) -> bool {
+    match slot(port) {
+        Ok(slot) => registry().remove(slot),
+        Err(_) => false,
+    }
+}
+
+/// Check whether a callback is registered for a port.
+pub fn is_registered(port: u8) -> bool {
+    match slot(port) {
+        Ok(slot) => registry().callbacks[slot].is_some(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    // The callbacks are never invoked, forgetting the packet avoids freeing it with libcsp.
+    fn noop() -> Callback {
+        Arc::new(core::mem::forget)
+    }
+
+    #[test]
+    fn port_bounds() {
+        assert_eq!(slot(0).unwrap(), 0);
+        assert_eq!(slot(MAX_PORT).unwrap(), MAX_PORT as usize);
+        assert_eq!(slot(CSP_ANY).unwrap(), ANY_SLOT);
+        assert_eq!(slot(MAX_PORT + 1).unwrap_err().code(), ErrorCode::Inval);
+        assert_eq!(slot(CSP_ANY - 1).unwrap_err().code(), ErrorCode::Inval);
+        assert!(!unregister(MAX_PORT + 1));
+        assert!(!is_registered(MAX_PORT + 1));
+    }
+
+    #[test]
+    fn unregister_keeps_port_bound() {
+        let mut registry = Registry::new();
+        let binds = Cell::new(0);
+        let bind = || {
+            binds.set(binds.get() + 1);
+            Ok(())
+        };
+        registry.insert(5, noop(), bind).unwrap();
+        assert_eq!(
+            registry.insert(5, noop(), bind).unwrap_err().code(),
+            ErrorCode::Used
+        );
+        assert_eq!(binds.get(), 1);
+
+        // Unregistering only removes the closure, the port stays bound to the trampoline.
+        assert!(registry.remove(5));
+        assert!(!registry.remove(5));
+        assert!(registry.bound[5]);
+        assert!(registry.lookup(5).is_none());
+        registry.insert(5, noop(), bind).unwrap();
+        assert_eq!(binds.get(), 1);
+
+        // The callback is not stored if the port could not be bound.
+        let failed = || Err(CspError::new("csp_bind_callback", ErrorCode::Used));
+        assert_eq!(
+            registry.insert(6, noop(), failed).unwrap_err().code(),
+            ErrorCode::Used
+        );
+        assert!(!registry.bound[6]);
+        assert!(registry.callbacks[6].is_none());
+    }
+
+    #[test]
+    fn lookup_falls_back_to_any() {
+        let mut registry = Registry::new();
+        let bind = || Ok(());
+        registry.insert(5, noop(), bind).unwrap();
+        assert!(registry.lookup(6).is_none());
+        registry.insert(ANY_SLOT, noop(), bind).unwrap();
+        let port = registry.lookup(5).unwrap();
+        let any = registry.lookup(6).unwrap();
+        assert!(!Arc::ptr_eq(&port, &any));
+        assert!(Arc::ptr_eq(&any, &registry.lookup(MAX_PORT + 1).unwrap()));
+    }
+}
@@ -0,0 +1,188 @@
+//! CAN interface on top of a Linux SocketCAN device.
+//!
+//! [CanInterface::builder] opens a SocketCAN device, for example `can0` or a virtual `vcan0`
+//! device, and adds a `libcsp` CAN interface for it. Packets are split into CAN frames with the
+//! CAN fragmentation protocol (CFP) and the received frames are read by an RX thread of the C
+//! driver. The interface must be enabled with the `can` option of the `libcsp-cargo-build`
+//! configuration.
+use core::ffi::c_int;
+use std::{ffi::CString, string::String};
+
+use crate::{
+    driver::{configure_added_iface, new_iface_name},
+    ffi,
+    iflist::IfaceRef,
+    iflist::IfaceStats,
+    CspError, CspStack, ErrorCode,
+};
+
+/// Maximum length of a network device name, without the NUL terminator.
+pub const DEVICE_NAME_MAX: usize = 15;
+
+const OP: &str = "csp_can_socketcan_open_and_add_interface";
+
+fn check_device(device: &str) -> Result<CString, CspError> {
+    if device.is_empty() || device.len() > DEVICE_NAME_MAX {
+        return Err(CspError::new(OP, ErrorCode::Inval));
+    }
+    CString::new(device).map_err(|_| CspError::new(OP, ErrorCode::Inval))
+}
+
+fn check_bitrate(bitrate: u32) -> Result<c_int, CspError> {
+    c_int::try_from(bitrate).map_err(|_| CspError::new(OP, ErrorCode::Inval))
+}
+
+/// Builder for a [CanInterface].
+#[derive(Debug, Clone)]
+pub struct CanInterfaceBuilder {
+    device: String,
+    name: String,
+    addr: u16,
+    netmask: u16,
+    is_default: bool,
+    bitrate: u32,
+    promisc: bool,
+}
+
+impl CanInterfaceBuilder {
+    fn new(device: String) -> Self {
+        Self {
+            device,
+            name: String::from("CAN"),
+            addr: 0,
+            netmask: 0,
+            is_default: false,
+            bitrate: 0,
+            promisc: false,
+        }
+    }
+
+    /// Name of the interface. Defaults to `CAN`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Host address on the subnet of the interface.
+    pub fn addr(mut self, addr: u16) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// Number of bits of the subnet mask.
+    pub fn netmask(mut self, netmask: u16) -> Self {
+        self.netmask = netmask;
+        self
+    }
+
+    /// Use the interface for destinations without another route.
+    pub fn default_iface(mut self, is_default: bool) -> Self {
+        self.is_default = is_default;
+        self
+    }
+
+    /// Bitrate of the device in bit/s. The default of 0 keeps the configured bitrate.
+    ///
+    /// Changing the bitrate requires the `libsocketcan` option of the `libcsp-cargo-build`
+    /// configuration and usually elevated privileges. Without `libsocketcan`, the bitrate is
+    /// ignored.
+    pub fn bitrate(mut self, bitrate: u32) -> Self {
+        self.bitrate = bitrate;
+        self
+    }
+
+    /// Receive all CAN frames instead of only the frames addressed to the interface.
+    pub fn promisc(mut self, promisc: bool) -> Self {
+        self.promisc = promisc;
+        self
+    }
+
+    /// Rust wrapper for [ffi::can::csp_can_socketcan_open_and_add_interface].
+    ///
+    /// Returns [ErrorCode::Inval] if the device name is empty, longer than [DEVICE_NAME_MAX] or
+    /// contains a NUL character, if the bitrate does not fit into a C `int`, or if the name
+    /// of the interface is empty, longer than [ffi::CSP_IFLIST_NAME_MAX] or contains a NUL
+    /// character. [ErrorCode::Already] is returned if an interface with the same name exists.
+    /// Errors of the driver, for example for a device which does not exist, are passed on.
+    pub fn build(self, _stack: &CspStack) -> Result<CanInterface, CspError> {
+        let device = check_device(&self.device)?;
+        let bitrate = check_bitrate(self.bitrate)?;
+        // The driver copies the name into its own context.
+        let name = new_iface_name(OP, &self.name)?;
+        let mut iface = core::ptr::null_mut();
+        // SAFETY: FFI call with valid strings and a valid output pointer.
+        let result = unsafe {
+            ffi::can::csp_can_socketcan_open_and_add_interface(
+                device.as_ptr(),
+                name.as_ptr(),
+                self.addr.into(),
+                bitrate,
+                self.promisc,
+                &mut iface,
+            )
+        };
+        CspError::check(OP, result)?;
+        let iface = IfaceRef::from_raw(iface).ok_or(CspError::new(OP, ErrorCode::Driver))?;
+        // SAFETY: The interface was just added by the driver and is never freed, because this
+        // module does not expose ffi::can::csp_can_socketcan_stop. The driver only sets the
+        // address, so the remaining configuration is applied here.
+        unsafe { configure_added_iface(OP, iface.as_ptr(), self.netmask, self.is_default)? };
+        Ok(CanInterface {
+            iface,
+            promisc: self.promisc,
+        })
+    }
+}
+
+/// SocketCAN interface handle, created with [CanInterface::builder].
+///
+/// The interface stays in the interface list for the rest of the program lifetime.
+#[derive(Debug, Copy, Clone)]
+pub struct CanInterface {
+    iface: IfaceRef,
+    promisc: bool,
+}
+
+impl CanInterface {
+    /// Start configuring an interface for the SocketCAN device with the given name.
+    pub fn builder(device: impl Into<String>) -> CanInterfaceBuilder {
+        CanInterfaceBuilder::new(device.into())
+    }
+
+    /// View of the interface.
+    pub fn iface(&self) -> IfaceRef {
+        self.iface
+    }
+
+    pub fn stats(&self) -> IfaceStats {
+        self.iface.stats()
+    }
+
+    pub fn promisc(&self) -> bool {
+        self.promisc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_validation() {
+        assert_eq!(check_device("vcan0"), Ok(CString::new("vcan0").unwrap()));
+        assert!(check_device("abcdefghijklmno").is_ok());
+        for device in ["", "abcdefghijklmnop", "vcan\0x"] {
+            assert_eq!(check_device(device).unwrap_err().code(), ErrorCode::Inval);
+        }
+    }
+
+    #[test]
+    fn bitrate_validation() {
+        assert_eq!(check_bitrate(0), Ok(0));
+        assert_eq!(check_bitrate(1_000_000), Ok(1_000_000));
+        assert_eq!(
+            check_bitrate(u32::MAX).unwrap_err().code(),
+            ErrorCode::Inval
+        );
+    }
+}
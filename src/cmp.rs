@@ -0,0 +1,866 @@
+//! CSP Management Protocol (CMP).
+//!
+//! CMP requests are sent to the [ReservedPort::Cmp] port of a node and are answered by the
+//! standard service handler. Every message starts with a type byte ([REQUEST] or [REPLY]) and
+//! a [CmpCode] byte, followed by the message body. The bodies in this module use the packed
+//! `libcsp` wire layout, with all multi-byte fields in network byte order.
+//!
+//! The client functions perform a CMP transaction against a node. [process] and [handle] can be
+//! used by a Rust node to answer CMP requests with a custom [CmpHandler].
+use core::time::Duration;
+
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+
+use crate::{
+    csp_send, ffi, CspConnRef, CspError, CspStack, ErrorCode, MsgPriority, Packet, ReservedPort,
+};
+
+/// Type of a CMP request.
+pub const REQUEST: u8 = 0x00;
+/// Type of a CMP reply.
+pub const REPLY: u8 = 0xff;
+
+/// Size of the type and code header of a CMP message.
+pub const HEADER_LEN: usize = 2;
+
+pub const HOSTNAME_LEN: usize = 20;
+pub const MODEL_LEN: usize = 30;
+pub const IDENT_REV_LEN: usize = 20;
+pub const IDENT_DATE_LEN: usize = 12;
+pub const IDENT_TIME_LEN: usize = 9;
+pub const ROUTE_IFACE_LEN: usize = 11;
+pub const PEEK_MAX_LEN: usize = 200;
+pub const POKE_MAX_LEN: usize = 200;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum CmpCode {
+    Ident = 1,
+    RouteSetV1 = 2,
+    IfStats = 3,
+    Peek = 4,
+    Poke = 5,
+    Clock = 6,
+    RouteSetV2 = 7,
+}
+
+/// Body of a CMP message.
+pub trait CmpBody: Sized {
+    const CODE: CmpCode;
+
+    /// Size of the body on the wire.
+    fn wire_len(&self) -> usize;
+
+    /// Writes the body into the buffer, which is at least [Self::wire_len] bytes large.
+    fn encode(&self, buf: &mut [u8]);
+
+    /// Reads the body from the buffer. Returns [None] if the buffer is too short.
+    fn decode(buf: &[u8]) -> Option<Self>;
+}
+
+/// Returns the string stored in a NUL padded field. Invalid UTF-8 yields an empty string.
+fn field_str(field: &[u8]) -> &str {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    core::str::from_utf8(&field[..end]).unwrap_or("")
+}
+
+/// Stores a string into a NUL padded field. The string is truncated so that the field is always
+/// NUL terminated.
+fn set_field_str(field: &mut [u8], value: &str) {
+    field.fill(0);
+    let len = core::cmp::min(value.len(), field.len() - 1);
+    field[..len].copy_from_slice(&value.as_bytes()[..len]);
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(buf[offset..offset + 4].try_into().unwrap())
+}
+
+/// Identification of a node.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Ident {
+    pub hostname: [u8; HOSTNAME_LEN],
+    pub model: [u8; MODEL_LEN],
+    pub revision: [u8; IDENT_REV_LEN],
+    pub date: [u8; IDENT_DATE_LEN],
+    pub time: [u8; IDENT_TIME_LEN],
+}
+
+impl Ident {
+    pub const LEN: usize =
+        HOSTNAME_LEN + MODEL_LEN + IDENT_REV_LEN + IDENT_DATE_LEN + IDENT_TIME_LEN;
+
+    /// Strings which do not fit into their field are truncated.
+    pub fn new(hostname: &str, model: &str, revision: &str) -> Self {
+        let mut ident = Self::default();
+        set_field_str(&mut ident.hostname, hostname);
+        set_field_str(&mut ident.model, model);
+        set_field_str(&mut ident.revision, revision);
+        ident
+    }
+
+    /// Identification configured with [crate::CspConfig] when the stack was initialized.
+    pub fn from_conf(_stack: &CspStack) -> Self {
+        // SAFETY: FFI call. The configuration is only written during initialization.
+        let conf = unsafe { &*ffi::csp_get_conf() };
+        let cstr = |ptr: *const core::ffi::c_char| {
+            if ptr.is_null() {
+                return "";
+            }
+            // SAFETY: The configuration strings are NUL terminated static strings.
+            unsafe { core::ffi::CStr::from_ptr(ptr) }
+                .to_str()
+                .unwrap_or("")
+        };
+        Self::new(cstr(conf.hostname), cstr(conf.model), cstr(conf.revision))
+    }
+
+    pub fn hostname(&self) -> &str {
+        field_str(&self.hostname)
+    }
+
+    pub fn model(&self) -> &str {
+        field_str(&self.model)
+    }
+
+    pub fn revision(&self) -> &str {
+        field_str(&self.revision)
+    }
+
+    /// Build date of the node software.
+    pub fn date(&self) -> &str {
+        field_str(&self.date)
+    }
+
+    /// Build time of the node software.
+    pub fn time(&self) -> &str {
+        field_str(&self.time)
+    }
+}
+
+impl CmpBody for Ident {
+    const CODE: CmpCode = CmpCode::Ident;
+
+    fn wire_len(&self) -> usize {
+        Self::LEN
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        let mut offset = 0;
+        for field in [
+            &self.hostname[..],
+            &self.model,
+            &self.revision,
+            &self.date,
+            &self.time,
+        ] {
+            buf[offset..offset + field.len()].copy_from_slice(field);
+            offset += field.len();
+        }
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::LEN {
+            return None;
+        }
+        let mut ident = Self::default();
+        let mut offset = 0;
+        for field in [
+            &mut ident.hostname[..],
+            &mut ident.model,
+            &mut ident.revision,
+            &mut ident.date,
+            &mut ident.time,
+        ] {
+            let len = field.len();
+            field.copy_from_slice(&buf[offset..offset + len]);
+            offset += len;
+        }
+        Some(ident)
+    }
+}
+
+/// Route entry for CSP version 1 nodes.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct RouteSetV1 {
+    pub dest_node: u8,
+    pub next_hop_via: u8,
+    pub interface: [u8; ROUTE_IFACE_LEN],
+}
+
+impl RouteSetV1 {
+    pub const LEN: usize = 2 + ROUTE_IFACE_LEN;
+
+    pub fn new(dest_node: u8, next_hop_via: u8, interface: &str) -> Self {
+        let mut route = Self {
+            dest_node,
+            next_hop_via,
+            ..Default::default()
+        };
+        set_field_str(&mut route.interface, interface);
+        route
+    }
+
+    pub fn interface(&self) -> &str {
+        field_str(&self.interface)
+    }
+}
+
+impl CmpBody for RouteSetV1 {
+    const CODE: CmpCode = CmpCode::RouteSetV1;
+
+    fn wire_len(&self) -> usize {
+        Self::LEN
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0] = self.dest_node;
+        buf[1] = self.next_hop_via;
+        buf[2..Self::LEN].copy_from_slice(&self.interface);
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::LEN {
+            return None;
+        }
+        Some(Self {
+            dest_node: buf[0],
+            next_hop_via: buf[1],
+            interface: buf[2..Self::LEN].try_into().unwrap(),
+        })
+    }
+}
+
+/// Route entry for CSP version 2 nodes.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct RouteSetV2 {
+    pub dest_node: u16,
+    pub next_hop_via: u16,
+    pub netmask: u16,
+    pub interface: [u8; ROUTE_IFACE_LEN],
+}
+
+impl RouteSetV2 {
+    pub const LEN: usize = 6 + ROUTE_IFACE_LEN;
+
+    pub fn new(dest_node: u16, next_hop_via: u16, netmask: u16, interface: &str) -> Self {
+        let mut route = Self {
+            dest_node,
+            next_hop_via,
+            netmask,
+            ..Default::default()
+        };
+        set_field_str(&mut route.interface, interface);
+        route
+    }
+
+    pub fn interface(&self) -> &str {
+        field_str(&self.interface)
+    }
+}
+
+impl CmpBody for RouteSetV2 {
+    const CODE: CmpCode = CmpCode::RouteSetV2;
+
+    fn wire_len(&self) -> usize {
+        Self::LEN
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0..2].copy_from_slice(&self.dest_node.to_be_bytes());
+        buf[2..4].copy_from_slice(&self.next_hop_via.to_be_bytes());
+        buf[4..6].copy_from_slice(&self.netmask.to_be_bytes());
+        buf[6..Self::LEN].copy_from_slice(&self.interface);
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::LEN {
+            return None;
+        }
+        Some(Self {
+            dest_node: read_u16(buf, 0),
+            next_hop_via: read_u16(buf, 2),
+            netmask: read_u16(buf, 4),
+            interface: buf[6..Self::LEN].try_into().unwrap(),
+        })
+    }
+}
+
+/// Interface statistics. `libcsp` has no CMP request to configure interfaces, but this request
+/// can be used to query the state of an interface.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct IfStats {
+    pub interface: [u8; ROUTE_IFACE_LEN],
+    pub tx: u32,
+    pub rx: u32,
+    pub tx_error: u32,
+    pub rx_error: u32,
+    pub drop: u32,
+    pub autherr: u32,
+    pub frame: u32,
+    pub txbytes: u32,
+    pub rxbytes: u32,
+    pub irq: u32,
+}
+
+impl IfStats {
+    pub const LEN: usize = ROUTE_IFACE_LEN + 10 * 4;
+
+    /// Request for the statistics of the given interface.
+    pub fn request(interface: &str) -> Self {
+        let mut stats = Self::default();
+        set_field_str(&mut stats.interface, interface);
+        stats
+    }
+
+    pub fn interface(&self) -> &str {
+        field_str(&self.interface)
+    }
+
+    fn counters(&self) -> [u32; 10] {
+        [
+            self.tx,
+            self.rx,
+            self.tx_error,
+            self.rx_error,
+            self.drop,
+            self.autherr,
+            self.frame,
+            self.txbytes,
+            self.rxbytes,
+            self.irq,
+        ]
+    }
+}
+
+impl CmpBody for IfStats {
+    const CODE: CmpCode = CmpCode::IfStats;
+
+    fn wire_len(&self) -> usize {
+        Self::LEN
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[..ROUTE_IFACE_LEN].copy_from_slice(&self.interface);
+        for (i, counter) in self.counters().iter().enumerate() {
+            let offset = ROUTE_IFACE_LEN + i * 4;
+            buf[offset..offset + 4].copy_from_slice(&counter.to_be_bytes());
+        }
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::LEN {
+            return None;
+        }
+        let counter = |i: usize| read_u32(buf, ROUTE_IFACE_LEN + i * 4);
+        Some(Self {
+            interface: buf[..ROUTE_IFACE_LEN].try_into().unwrap(),
+            tx: counter(0),
+            rx: counter(1),
+            tx_error: counter(2),
+            rx_error: counter(3),
+            drop: counter(4),
+            autherr: counter(5),
+            frame: counter(6),
+            txbytes: counter(7),
+            rxbytes: counter(8),
+            irq: counter(9),
+        })
+    }
+}
+
+/// Size of the address and length fields of [Peek] and [Poke].
+const MEM_HEADER_LEN: usize = 5;
+
+fn encode_mem(buf: &mut [u8], addr: u32, len: u8, data: &[u8]) {
+    buf[0..4].copy_from_slice(&addr.to_be_bytes());
+    buf[4] = len;
+    buf[MEM_HEADER_LEN..MEM_HEADER_LEN + data.len()].copy_from_slice(data);
+}
+
+/// Reads the address, the length and the data of a [Peek] or [Poke] body. Only the first
+/// `len` bytes of the data are kept.
+fn decode_mem<const N: usize>(buf: &[u8]) -> Option<(u32, u8, [u8; N])> {
+    if buf.len() < MEM_HEADER_LEN + N {
+        return None;
+    }
+    let len = buf[4];
+    if len as usize > N {
+        return None;
+    }
+    let mut data = [0; N];
+    data[..len as usize].copy_from_slice(&buf[MEM_HEADER_LEN..MEM_HEADER_LEN + len as usize]);
+    Some((read_u32(buf, 0), len, data))
+}
+
+/// Memory read. For a request, only the address and length are relevant, and the reply
+/// contains the memory content.
+///
+/// Like `libcsp`, the full data field is always sent, so the body has the fixed size [Self::LEN].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Peek {
+    pub addr: u32,
+    len: u8,
+    data: [u8; PEEK_MAX_LEN],
+}
+
+impl Peek {
+    pub const MAX_LEN: usize = PEEK_MAX_LEN;
+    pub const LEN: usize = MEM_HEADER_LEN + PEEK_MAX_LEN;
+
+    /// Returns [None] if the length exceeds [PEEK_MAX_LEN].
+    pub fn request(addr: u32, len: usize) -> Option<Self> {
+        if len > PEEK_MAX_LEN {
+            return None;
+        }
+        Some(Self {
+            addr,
+            len: len as u8,
+            data: [0; PEEK_MAX_LEN],
+        })
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+impl CmpBody for Peek {
+    const CODE: CmpCode = CmpCode::Peek;
+
+    fn wire_len(&self) -> usize {
+        Self::LEN
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        encode_mem(buf, self.addr, self.len, &self.data);
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let (addr, len, data) = decode_mem(buf)?;
+        Some(Self { addr, len, data })
+    }
+}
+
+/// Memory write of the data to the address. The reply repeats the request.
+///
+/// Like `libcsp`, the full data field is always sent, so the body has the fixed size [Self::LEN].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Poke {
+    pub addr: u32,
+    len: u8,
+    data: [u8; POKE_MAX_LEN],
+}
+
+impl Poke {
+    pub const MAX_LEN: usize = POKE_MAX_LEN;
+    pub const LEN: usize = MEM_HEADER_LEN + POKE_MAX_LEN;
+
+    /// Returns [None] if the data is larger than [POKE_MAX_LEN].
+    pub fn request(addr: u32, data: &[u8]) -> Option<Self> {
+        if data.len() > POKE_MAX_LEN {
+            return None;
+        }
+        let mut poke = Self {
+            addr,
+            len: data.len() as u8,
+            data: [0; POKE_MAX_LEN],
+        };
+        poke.data[..data.len()].copy_from_slice(data);
+        Some(poke)
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.len as usize]
+    }
+}
+
+impl CmpBody for Poke {
+    const CODE: CmpCode = CmpCode::Poke;
+
+    fn wire_len(&self) -> usize {
+        Self::LEN
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        encode_mem(buf, self.addr, self.len, &self.data);
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        let (addr, len, data) = decode_mem(buf)?;
+        Some(Self { addr, len, data })
+    }
+}
+
+/// Clock of a node. A request with a zero timestamp only reads the clock, otherwise the clock
+/// is set before it is read back.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct Clock {
+    pub tv_sec: u32,
+    pub tv_nsec: u32,
+}
+
+impl Clock {
+    pub const LEN: usize = 8;
+
+    pub fn is_zero(&self) -> bool {
+        self.tv_sec == 0 && self.tv_nsec == 0
+    }
+}
+
+impl From<Duration> for Clock {
+    fn from(time: Duration) -> Self {
+        Self {
+            tv_sec: time.as_secs() as u32,
+            tv_nsec: time.subsec_nanos(),
+        }
+    }
+}
+
+impl From<Clock> for Duration {
+    fn from(clock: Clock) -> Self {
+        Duration::new(clock.tv_sec.into(), clock.tv_nsec)
+    }
+}
+
+impl CmpBody for Clock {
+    const CODE: CmpCode = CmpCode::Clock;
+
+    fn wire_len(&self) -> usize {
+        Self::LEN
+    }
+
+    fn encode(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.tv_sec.to_be_bytes());
+        buf[4..8].copy_from_slice(&self.tv_nsec.to_be_bytes());
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < Self::LEN {
+            return None;
+        }
+        Some(Self {
+            tv_sec: read_u32(buf, 0),
+            tv_nsec: read_u32(buf, 4),
+        })
+    }
+}
+
+/// Performs a CMP transaction with the given request body and returns the reply body.
+///
+/// Returns [ErrorCode::TimedOut] if the node did not answer and [ErrorCode::Inval] if the
+/// reply is not a valid reply to the request.
+pub fn transaction<B: CmpBody>(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    request: &B,
+) -> Result<B, CspError> {
+    let mut buf = [0; ffi::CSP_BUFFER_SIZE];
+    let len = HEADER_LEN + request.wire_len();
+    buf[0] = REQUEST;
+    buf[1] = B::CODE.into();
+    request.encode(&mut buf[HEADER_LEN..len]);
+    let mut reply = [0; ffi::CSP_BUFFER_SIZE];
+    let reply_len = stack.transaction(
+        MsgPriority::Normal,
+        node,
+        ReservedPort::Cmp as u8,
+        timeout,
+        &buf[..len],
+        &mut reply,
+        Some(len),
+    )?;
+    if reply_len < HEADER_LEN || reply[0] != REPLY || reply[1] != u8::from(B::CODE) {
+        return Err(CspError::new("csp_cmp", ErrorCode::Inval));
+    }
+    B::decode(&reply[HEADER_LEN..reply_len]).ok_or(CspError::new("csp_cmp", ErrorCode::Inval))
+}
+
+/// Request the identification of a node.
+pub fn ident(stack: &CspStack, node: u16, timeout: Duration) -> Result<Ident, CspError> {
+    transaction(stack, node, timeout, &Ident::default())
+}
+
+/// Set a route on a CSP version 1 node.
+pub fn route_set_v1(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    route: &RouteSetV1,
+) -> Result<(), CspError> {
+    transaction(stack, node, timeout, route).map(|_| ())
+}
+
+/// Set a route on a CSP version 2 node.
+pub fn route_set_v2(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    route: &RouteSetV2,
+) -> Result<(), CspError> {
+    transaction(stack, node, timeout, route).map(|_| ())
+}
+
+/// Request the statistics of an interface of a node.
+pub fn if_stats(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    interface: &str,
+) -> Result<IfStats, CspError> {
+    transaction(stack, node, timeout, &IfStats::request(interface))
+}
+
+/// Read memory of a node. The returned [Peek] contains the memory content.
+///
+/// Returns [ErrorCode::Inval] if the length exceeds [PEEK_MAX_LEN].
+pub fn peek(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    addr: u32,
+    len: usize,
+) -> Result<Peek, CspError> {
+    let request = Peek::request(addr, len).ok_or(CspError::new("csp_cmp", ErrorCode::Inval))?;
+    transaction(stack, node, timeout, &request)
+}
+
+/// Write memory of a node.
+///
+/// Returns [ErrorCode::Inval] if the data is larger than [POKE_MAX_LEN].
+pub fn poke(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    addr: u32,
+    data: &[u8],
+) -> Result<(), CspError> {
+    let request = Poke::request(addr, data).ok_or(CspError::new("csp_cmp", ErrorCode::Inval))?;
+    transaction(stack, node, timeout, &request).map(|_| ())
+}
+
+/// Read the clock of a node.
+pub fn clock_get(stack: &CspStack, node: u16, timeout: Duration) -> Result<Clock, CspError> {
+    transaction(stack, node, timeout, &Clock::default())
+}
+
+/// Set the clock of a node. Returns the clock which was read back after setting it.
+pub fn clock_set(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    clock: Clock,
+) -> Result<Clock, CspError> {
+    transaction(stack, node, timeout, &clock)
+}
+
+/// Server side of CMP. Requests which are not implemented are answered with
+/// [ErrorCode::NotSup] and no reply is sent in that case.
+pub trait CmpHandler {
+    /// Identification of the node.
+    fn ident(&mut self) -> Ident;
+
+    fn route_set_v1(&mut self, _route: &RouteSetV1) -> Result<(), CspError> {
+        Err(CspError::new("csp_cmp", ErrorCode::NotSup))
+    }
+
+    fn route_set_v2(&mut self, _route: &RouteSetV2) -> Result<(), CspError> {
+        Err(CspError::new("csp_cmp", ErrorCode::NotSup))
+    }
+
+    /// Statistics of the requested interface.
+    fn if_stats(&mut self, _interface: &str) -> Result<IfStats, CspError> {
+        Err(CspError::new("csp_cmp", ErrorCode::NotSup))
+    }
+
+    /// Fill the reply data for the requested memory region.
+    fn peek(&mut self, _addr: u32, _data: &mut [u8]) -> Result<(), CspError> {
+        Err(CspError::new("csp_cmp", ErrorCode::NotSup))
+    }
+
+    fn poke(&mut self, _addr: u32, _data: &[u8]) -> Result<(), CspError> {
+        Err(CspError::new("csp_cmp", ErrorCode::NotSup))
+    }
+
+    /// Set the clock if `set` is not [None] and return the current clock.
+    fn clock(&mut self, _set: Option<Clock>) -> Result<Clock, CspError> {
+        Err(CspError::new("csp_cmp", ErrorCode::NotSup))
+    }
+}
+
+/// [CmpHandler] which answers ident requests with the identification configured with
+/// [crate::CspConfig].
+#[derive(Debug, Copy, Clone)]
+pub struct ConfIdent(pub CspStack);
+
+impl CmpHandler for ConfIdent {
+    fn ident(&mut self) -> Ident {
+        Ident::from_conf(&self.0)
+    }
+}
+
+fn encode_reply<B: CmpBody>(packet: &mut Packet, body: &B) -> Result<(), CspError> {
+    let len = HEADER_LEN + body.wire_len();
+    if len > Packet::CAPACITY {
+        return Err(CspError::new("csp_cmp", ErrorCode::Inval));
+    }
+    packet.set_len(len)?;
+    packet[0] = REPLY;
+    body.encode(&mut packet[HEADER_LEN..len]);
+    Ok(())
+}
+
+/// Turns a CMP request packet into the reply packet.
+///
+/// Returns [ErrorCode::Inval] if the packet is not a valid CMP request and the error of the
+/// handler if the request could not be processed.
+pub fn process(handler: &mut impl CmpHandler, packet: &mut Packet) -> Result<(), CspError> {
+    let invalid = CspError::new("csp_cmp", ErrorCode::Inval);
+    if packet.len() < HEADER_LEN || packet[0] != REQUEST {
+        return Err(invalid);
+    }
+    let code = CmpCode::try_from(packet[1]).map_err(|_| invalid)?;
+    let body = &packet[HEADER_LEN..];
+    match code {
+        CmpCode::Ident => {
+            let ident = handler.ident();
+            encode_reply(packet, &ident)
+        }
+        CmpCode::RouteSetV1 => {
+            let route = RouteSetV1::decode(body).ok_or(invalid)?;
+            handler.route_set_v1(&route)?;
+            encode_reply(packet, &route)
+        }
+        CmpCode::RouteSetV2 => {
+            let route = RouteSetV2::decode(body).ok_or(invalid)?;
+            handler.route_set_v2(&route)?;
+            encode_reply(packet, &route)
+        }
+        CmpCode::IfStats => {
+            let request = IfStats::decode(body).ok_or(invalid)?;
+            let mut stats = handler.if_stats(request.interface())?;
+            stats.interface = request.interface;
+            encode_reply(packet, &stats)
+        }
+        CmpCode::Peek => {
+            let mut peek = Peek::decode(body).ok_or(invalid)?;
+            let len = peek.len as usize;
+            handler.peek(peek.addr, &mut peek.data[..len])?;
+            encode_reply(packet, &peek)
+        }
+        CmpCode::Poke => {
+            let poke = Poke::decode(body).ok_or(invalid)?;
+            handler.poke(poke.addr, poke.data())?;
+            encode_reply(packet, &poke)
+        }
+        CmpCode::Clock => {
+            let request = Clock::decode(body).ok_or(invalid)?;
+            let set = if request.is_zero() {
+                None
+            } else {
+                Some(request)
+            };
+            let clock = handler.clock(set)?;
+            encode_reply(packet, &clock)
+        }
+    }
+}
+
+/// Processes a CMP request received on a connection and sends the reply. The packet is dropped
+/// if the request could not be processed.
+pub fn handle(
+    handler: &mut impl CmpHandler,
+    conn: &mut CspConnRef,
+    mut packet: Packet,
+) -> Result<(), CspError> {
+    process(handler, &mut packet)?;
+    csp_send(conn, packet);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<B: CmpBody + PartialEq + core::fmt::Debug>(body: B) {
+        let mut buf = [0; ffi::CSP_BUFFER_SIZE];
+        body.encode(&mut buf[..body.wire_len()]);
+        assert_eq!(B::decode(&buf[..body.wire_len()]).unwrap(), body);
+    }
+
+    #[test]
+    fn wire_sizes() {
+        assert_eq!(Ident::LEN, 91);
+        assert_eq!(RouteSetV1::LEN, 13);
+        assert_eq!(RouteSetV2::LEN, 17);
+        assert_eq!(IfStats::LEN, 51);
+        assert_eq!(Peek::LEN, 205);
+        assert_eq!(Poke::LEN, 205);
+        assert_eq!(Clock::LEN, 8);
+    }
+
+    #[test]
+    fn ident_fields() {
+        let ident = Ident::new("obc", "a-model-name", "a-very-long-revision-string");
+        assert_eq!(ident.hostname(), "obc");
+        assert_eq!(ident.model(), "a-model-name");
+        assert_eq!(ident.revision(), "a-very-long-revisio");
+        round_trip(ident);
+    }
+
+    #[test]
+    fn big_endian_layout() {
+        let route = RouteSetV2::new(0x0102, 0x0304, 0x0506, "CAN");
+        let mut buf = [0; RouteSetV2::LEN];
+        route.encode(&mut buf);
+        assert_eq!(&buf[..9], &[1, 2, 3, 4, 5, 6, b'C', b'A', b'N']);
+        round_trip(route);
+        round_trip(RouteSetV1::new(3, 4, "I2C"));
+        round_trip(Clock {
+            tv_sec: 0x01020304,
+            tv_nsec: 5,
+        });
+        let mut stats = IfStats::request("LOOP");
+        stats.rx = 7;
+        stats.irq = 9;
+        round_trip(stats);
+    }
+
+    #[test]
+    fn mem_access() {
+        // CMP_SIZE(poke) of libcsp: type, code, address, length and the full data field.
+        let mut c_layout = [0u8; HEADER_LEN + Poke::LEN];
+        c_layout[..HEADER_LEN + 8].copy_from_slice(&[REQUEST, 5, 0, 0, 0x10, 0, 3, 1, 2, 3]);
+        assert_eq!(c_layout.len(), 2 + 4 + 1 + POKE_MAX_LEN);
+
+        let poke = Poke::request(0x1000, &[1, 2, 3]).unwrap();
+        let mut buf = [0; HEADER_LEN + Poke::LEN];
+        buf[0] = REQUEST;
+        buf[1] = Poke::CODE.into();
+        poke.encode(&mut buf[HEADER_LEN..HEADER_LEN + poke.wire_len()]);
+        assert_eq!(buf, c_layout);
+        let decoded = Poke::decode(&c_layout[HEADER_LEN..]).unwrap();
+        assert_eq!(decoded, poke);
+        assert_eq!(decoded.data(), &[1, 2, 3]);
+
+        // Peek reply of libcsp with the memory content.
+        let mut c_layout = [0u8; Peek::LEN];
+        c_layout[..7].copy_from_slice(&[0x12, 0x34, 0x56, 0x78, 2, 0xab, 0xcd]);
+        let peek = Peek::decode(&c_layout).unwrap();
+        assert_eq!(peek.addr, 0x12345678);
+        assert_eq!(peek.data(), &[0xab, 0xcd]);
+        round_trip(peek);
+
+        assert!(Peek::request(0, PEEK_MAX_LEN + 1).is_none());
+        assert!(Peek::decode(&c_layout[..Peek::LEN - 1]).is_none());
+        c_layout[4] = PEEK_MAX_LEN as u8 + 1;
+        assert!(Peek::decode(&c_layout).is_none());
+        assert_eq!(Peek::CODE, CmpCode::Peek);
+        assert_eq!(Poke::CODE, CmpCode::Poke);
+    }
+}
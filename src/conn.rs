@@ -0,0 +1,193 @@
+//! Connection table introspection.
+//!
+//! `libcsp` keeps all connections in a static table with [ffi::CSP_CONN_MAX] entries. [iter]
+//! returns snapshots of all entries, including closed ones, so the connection table can be
+//! reported in telemetry instead of printed with [crate::csp_conn_print_table].
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec};
+use core::ptr::addr_of;
+
+use crate::{ffi, ConnState, ConnType, ConnectOpts, CspId, CspStack, RdpState};
+
+/// Snapshot of an entry of the connection table.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ConnInfo {
+    /// Unknown states are reported as [ConnState::Closed].
+    pub state: ConnState,
+    /// Unknown types are reported as [ConnType::Client].
+    pub kind: ConnType,
+    /// Header of incoming packets.
+    pub idin: CspId,
+    /// Header of outgoing packets.
+    pub idout: CspId,
+    /// Source port of an outgoing connection, which is reserved while it is open.
+    pub sport_outgoing: u8,
+    /// Time of the last activity in milliseconds.
+    pub timestamp: u32,
+    pub opts: ConnectOpts,
+    /// State of RDP connections, [None] for connections without RDP.
+    pub rdp_state: Option<RdpState>,
+}
+
+impl ConnInfo {
+    /// Read a connection. The connection is modified by other tasks, so the fields of the
+    /// snapshot may belong to slightly different points in time.
+    ///
+    /// # Safety
+    ///
+    /// `conn` must point to a connection.
+    unsafe fn read(conn: *const ffi::csp_conn_t) -> Self {
+        // SAFETY: Volatile reads of the valid connection, because the fields are modified by
+        // other tasks.
+        unsafe {
+            let opts = ConnectOpts::from_bits_retain(addr_of!((*conn).opts).read_volatile());
+            let rdp_state = if opts.contains(ConnectOpts::RDP) {
+                RdpState::try_from(addr_of!((*conn).rdp.state).read_volatile()).ok()
+            } else {
+                None
+            };
+            Self {
+                state: u8::try_from(addr_of!((*conn).state).read_volatile())
+                    .ok()
+                    .and_then(|state| ConnState::try_from(state).ok())
+                    .unwrap_or(ConnState::Closed),
+                kind: u8::try_from(addr_of!((*conn).type_).read_volatile())
+                    .ok()
+                    .and_then(|kind| ConnType::try_from(kind).ok())
+                    .unwrap_or(ConnType::Client),
+                idin: addr_of!((*conn).idin).read_volatile().into(),
+                idout: addr_of!((*conn).idout).read_volatile().into(),
+                sport_outgoing: addr_of!((*conn).sport_outgoing).read_volatile(),
+                timestamp: addr_of!((*conn).timestamp).read_volatile(),
+                opts,
+                rdp_state,
+            }
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == ConnState::Open
+    }
+}
+
+/// Iterator over the entries of the connection table, created with [iter].
+#[derive(Debug)]
+pub struct Iter {
+    conns: *const ffi::csp_conn_t,
+    len: usize,
+    next: usize,
+}
+
+impl Iterator for Iter {
+    type Item = ConnInfo;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.len {
+            return None;
+        }
+        // SAFETY: The connection array has `len` entries and is never freed.
+        let info = unsafe { ConnInfo::read(self.conns.add(self.next)) };
+        self.next += 1;
+        Some(info)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter {}
+
+/// Iterator over snapshots of all entries of the connection table, see
+/// [ffi::csp_conn_get_array]. Use [ConnInfo::is_open] to skip the unused entries.
+pub fn iter(_stack: &CspStack) -> Iter {
+    let mut len = 0;
+    // SAFETY: FFI call with a valid output pointer.
+    let conns = unsafe { ffi::csp_conn_get_array(&mut len) };
+    Iter {
+        conns,
+        len: if conns.is_null() { 0 } else { len },
+        next: 0,
+    }
+}
+
+/// Maximum length of a line of [table_string], including the newline.
+#[cfg(feature = "alloc")]
+const TABLE_LINE_SIZE: usize = 100;
+
+/// Size of the buffer for [table_string]. `libcsp` prints at most one line for every entry of
+/// the connection table.
+#[cfg(feature = "alloc")]
+const TABLE_STR_SIZE: usize = ffi::CSP_CONN_MAX * TABLE_LINE_SIZE;
+
+/// Rust wrapper for [ffi::csp_conn_print_table_str], which returns the connection table in the
+/// format of [crate::csp_conn_print_table].
+#[cfg(feature = "alloc")]
+pub fn table_string(_stack: &CspStack) -> String {
+    // libcsp appends to the buffer, so it must be zeroed, and it does not count the
+    // terminating NUL character.
+    let mut buf = vec![0u8; TABLE_STR_SIZE + 1];
+    let size = core::ffi::c_int::try_from(TABLE_STR_SIZE).unwrap_or(core::ffi::c_int::MAX);
+    // SAFETY: FFI call with a zeroed buffer which is larger than the passed size.
+    unsafe { ffi::csp_conn_print_table_str(buf.as_mut_ptr().cast(), size) };
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    String::from_utf8(buf).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HeaderFlags, MsgPriority};
+
+    #[test]
+    fn read_conn() {
+        // SAFETY: The connection only consists of integers, pointers and optional function
+        // pointers, which may be zero.
+        let mut conn: ffi::csp_conn_t = unsafe { core::mem::zeroed() };
+        // SAFETY: The connection is valid.
+        let info = unsafe { ConnInfo::read(&conn) };
+        assert!(!info.is_open());
+        assert_eq!(info.kind, ConnType::Client);
+        assert_eq!(info.rdp_state, None);
+
+        conn.state = 1;
+        conn.type_ = 1;
+        conn.opts = ConnectOpts::RDP.bits();
+        conn.rdp.state = RdpState::Open.into();
+        conn.idin = ffi::csp_id_t {
+            pri: 2,
+            flags: HeaderFlags::RDP.bits(),
+            src: 5,
+            dst: 7,
+            dport: 10,
+            sport: 33,
+        };
+        conn.sport_outgoing = 33;
+        conn.timestamp = 1234;
+        // SAFETY: The connection is valid.
+        let info = unsafe { ConnInfo::read(&conn) };
+        assert!(info.is_open());
+        assert_eq!(info.kind, ConnType::Server);
+        assert_eq!(info.opts, ConnectOpts::RDP);
+        assert_eq!(info.rdp_state, Some(RdpState::Open));
+        assert_eq!(
+            info.idin,
+            CspId {
+                prio: MsgPriority::Normal,
+                flags: HeaderFlags::RDP,
+                src: 5,
+                dst: 7,
+                dport: 10,
+                sport: 33,
+            }
+        );
+        assert_eq!(info.sport_outgoing, 33);
+        assert_eq!(info.timestamp, 1234);
+
+        conn.state = 7;
+        // SAFETY: The connection is valid.
+        assert_eq!(unsafe { ConnInfo::read(&conn) }.state, ConnState::Closed);
+    }
+}
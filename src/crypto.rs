@@ -0,0 +1,221 @@
+//! Encryption for the TUN interface.
+//!
+//! The TUN interface of `libcsp` encrypts tunneled packets with the `csp_crypto_encrypt` and
+//! `csp_crypto_decrypt` hooks. A [CryptoProvider] implements both operations. With the `hooks`
+//! feature, the default implementations of [crate::hooks::Hooks::encrypt] and
+//! [crate::hooks::Hooks::decrypt] use the provider installed with [install].
+//!
+//! [AeadProvider] is a reference provider based on the ChaCha20-Poly1305 AEAD construction of
+//! RFC 8439, which is implemented by the RustCrypto `chacha20poly1305` crate. This module is
+//! enabled with the `crypto` feature.
+use core::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(feature = "std")]
+use std::{
+    boxed::Box,
+    sync::{PoisonError, RwLock},
+};
+
+use chacha20poly1305::{AeadInPlace, ChaCha20Poly1305, KeyInit};
+
+use crate::{ffi, CspError, ErrorCode};
+
+/// Encryption of tunneled packets.
+///
+/// The provider is invoked from the `libcsp` tasks which route packets through the TUN
+/// interface.
+pub trait CryptoProvider: Send + Sync {
+    /// Encrypt a message into the ciphertext buffer. Returns the length of the ciphertext.
+    fn encrypt(&self, msg: &[u8], ciphertext: &mut [u8]) -> Result<usize, CspError>;
+
+    /// Decrypt a ciphertext into the message buffer. Returns the length of the message.
+    fn decrypt(&self, ciphertext: &[u8], msg: &mut [u8]) -> Result<usize, CspError>;
+}
+
+#[cfg(feature = "std")]
+static PROVIDER: RwLock<Option<Box<dyn CryptoProvider>>> = RwLock::new(None);
+
+/// Install the provider used by the TUN interface. Returns the previously installed provider.
+#[cfg(feature = "std")]
+pub fn install(provider: impl CryptoProvider + 'static) -> Option<Box<dyn CryptoProvider>> {
+    PROVIDER
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(Box::new(provider))
+}
+
+/// Remove the installed provider.
+#[cfg(feature = "std")]
+pub fn uninstall() -> Option<Box<dyn CryptoProvider>> {
+    PROVIDER
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+}
+
+/// Encrypt with the installed provider. Returns [ErrorCode::NotSup] if no provider is
+/// installed.
+#[cfg(feature = "hooks")]
+pub(crate) fn encrypt(msg: &[u8], ciphertext: &mut [u8]) -> Result<usize, CspError> {
+    match PROVIDER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_deref()
+    {
+        Some(provider) => provider.encrypt(msg, ciphertext),
+        None => Err(CspError::new("csp_crypto_encrypt", ErrorCode::NotSup)),
+    }
+}
+
+/// Decrypt with the installed provider. Returns [ErrorCode::NotSup] if no provider is
+/// installed.
+#[cfg(feature = "hooks")]
+pub(crate) fn decrypt(ciphertext: &[u8], msg: &mut [u8]) -> Result<usize, CspError> {
+    match PROVIDER
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_deref()
+    {
+        Some(provider) => provider.decrypt(ciphertext, msg),
+        None => Err(CspError::new("csp_crypto_decrypt", ErrorCode::NotSup)),
+    }
+}
+
+/// Size of a ChaCha20-Poly1305 key.
+pub const KEY_LEN: usize = 32;
+/// Size of a ChaCha20-Poly1305 nonce.
+pub const NONCE_LEN: usize = 12;
+/// Size of a Poly1305 authentication tag.
+pub const TAG_LEN: usize = 16;
+
+/// Reference [CryptoProvider] based on the ChaCha20-Poly1305 AEAD construction of RFC 8439.
+///
+/// The ciphertext consists of the nonce, the encrypted message and the authentication tag, so
+/// it is [AeadProvider::OVERHEAD] bytes longer than the message. Each nonce starts with the
+/// 32 bit prefix passed to [AeadProvider::new], followed by a 64 bit message counter. All
+/// nodes which share a key must therefore use different prefixes, and a node must use a new
+/// prefix or key after a restart, for example by including a boot counter in the prefix.
+/// Replayed messages are not detected.
+pub struct AeadProvider {
+    cipher: ChaCha20Poly1305,
+    nonce_prefix: u32,
+    counter: AtomicU64,
+}
+
+impl core::fmt::Debug for AeadProvider {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AeadProvider")
+            .field("nonce_prefix", &self.nonce_prefix)
+            .field("counter", &self.counter)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AeadProvider {
+    /// Size difference between the ciphertext and the message.
+    pub const OVERHEAD: usize = NONCE_LEN + TAG_LEN;
+
+    pub fn new(key: &[u8; KEY_LEN], nonce_prefix: u32) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(key.into()),
+            nonce_prefix,
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+impl CryptoProvider for AeadProvider {
+    /// Returns [ErrorCode::NoBufs] if the ciphertext does not fit into the buffer.
+    fn encrypt(&self, msg: &[u8], ciphertext: &mut [u8]) -> Result<usize, CspError> {
+        let len = msg.len() + Self::OVERHEAD;
+        if ciphertext.len() < len {
+            return Err(CspError::new("csp_crypto_encrypt", ErrorCode::NoBufs));
+        }
+        let mut nonce = [0; NONCE_LEN];
+        nonce[..4].copy_from_slice(&self.nonce_prefix.to_be_bytes());
+        nonce[4..].copy_from_slice(&self.counter.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+        let (nonce_buf, rest) = ciphertext.split_at_mut(NONCE_LEN);
+        nonce_buf.copy_from_slice(&nonce);
+        let (data, rest) = rest.split_at_mut(msg.len());
+        data.copy_from_slice(msg);
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(&nonce.into(), &[], data)
+            .map_err(|_| CspError::new("csp_crypto_encrypt", ErrorCode::Inval))?;
+        rest[..TAG_LEN].copy_from_slice(&tag);
+        Ok(len)
+    }
+
+    /// Returns [ErrorCode::Inval] if the ciphertext is too short or the message does not fit
+    /// into the buffer, and [ErrorCode::Hmac] if the authentication failed. The tag is verified
+    /// before the message is decrypted, and the message buffer is not modified if the
+    /// decryption fails.
+    fn decrypt(&self, ciphertext: &[u8], msg: &mut [u8]) -> Result<usize, CspError> {
+        let invalid = CspError::new("csp_crypto_decrypt", ErrorCode::Inval);
+        let len = ciphertext
+            .len()
+            .checked_sub(Self::OVERHEAD)
+            .ok_or(invalid)?;
+        let mut buf = [0; ffi::CSP_BUFFER_SIZE];
+        if msg.len() < len || buf.len() < len {
+            return Err(invalid);
+        }
+        let (nonce, rest) = ciphertext.split_at(NONCE_LEN);
+        let (data, tag) = rest.split_at(len);
+        // Decrypt into a scratch buffer, so the message buffer is only written after the
+        // authentication succeeded.
+        let buf = &mut buf[..len];
+        buf.copy_from_slice(data);
+        self.cipher
+            .decrypt_in_place_detached(nonce.into(), &[], buf, tag.into())
+            .map_err(|_| CspError::new("csp_crypto_decrypt", ErrorCode::Hmac))?;
+        msg[..len].copy_from_slice(buf);
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn provider_round_trip() {
+        let provider = AeadProvider::new(&[7; KEY_LEN], 1);
+        let msg = [1, 2, 3, 4, 5];
+        let mut ciphertext = [0; 64];
+        let len = provider.encrypt(&msg, &mut ciphertext).unwrap();
+        assert_eq!(len, msg.len() + AeadProvider::OVERHEAD);
+        // The counter advances, so the same message is encrypted differently.
+        let mut second = [0; 64];
+        provider.encrypt(&msg, &mut second).unwrap();
+        assert_ne!(ciphertext[..len], second[..len]);
+
+        let mut decrypted = [0; 16];
+        assert_eq!(
+            provider
+                .decrypt(&ciphertext[..len], &mut decrypted)
+                .unwrap(),
+            msg.len()
+        );
+        assert_eq!(decrypted[..msg.len()], msg);
+
+        ciphertext[NONCE_LEN] ^= 1;
+        let mut untouched = [0x55; 16];
+        assert_eq!(
+            provider
+                .decrypt(&ciphertext[..len], &mut untouched)
+                .unwrap_err()
+                .code(),
+            ErrorCode::Hmac
+        );
+        assert_eq!(untouched, [0x55; 16]);
+        assert_eq!(
+            provider.decrypt(&ciphertext[..4], &mut decrypted),
+            Err(CspError::new("csp_crypto_decrypt", ErrorCode::Inval))
+        );
+        assert_eq!(
+            provider.encrypt(&msg, &mut [0; 8]).unwrap_err().code(),
+            ErrorCode::NoBufs
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! CSP interfaces implemented in Rust.
+//!
+//! A link layer implements [CspInterfaceDriver] and is registered with [register], which adds
+//! a new interface to the interface list. The router passes outgoing packets to
+//! [CspInterfaceDriver::transmit] through a C `nexthop` trampoline, and the link layer passes
+//! received packets to the router with the returned [RxHandle].
+//!
+//! `libcsp` updates the `tx`, `txbytes` and `tx_error` counters of the interface for every
+//! transmitted packet and the `rx` and `rxbytes` counters for every packet routed from the
+//! interface. The [RxHandle] updates the error counters for packets which can not be passed to
+//! the router.
+//!
+//! Interfaces are referenced by the routing table and can not be freed, so the interface and
+//! its driver are kept alive for the rest of the program lifetime.
+use core::ffi::c_int;
+use std::{
+    boxed::Box,
+    ffi::CString,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr::NonNull,
+};
+
+use crate::{ffi, iflist::IfaceRef, CspError, CspId, CspStack, ErrorCode, Packet};
+
+/// Link layer of a CSP interface.
+///
+/// The driver is invoked from the `libcsp` tasks which send packets, for example the router
+/// task, and possibly from several tasks at once.
+pub trait CspInterfaceDriver: Send + Sync + 'static {
+    /// Transmit a packet. The CSP header of the packet is available with [Packet::id]. `via` is
+    /// the address of the next hop and `from_me` is set if the packet originates from this
+    /// node.
+    ///
+    /// The driver takes the ownership of the packet in any case. An error is counted as a
+    /// transmit error of the interface.
+    fn transmit(&self, via: u16, packet: Packet, from_me: bool) -> Result<(), CspError>;
+}
+
+struct Interface<D> {
+    iface: ffi::csp_iface_t,
+    // Referenced by the interface.
+    _name: CString,
+    driver: D,
+}
+
+unsafe extern "C" fn nexthop<D: CspInterfaceDriver>(
+    iface: *mut ffi::csp_iface_t,
+    via: u16,
+    packet: *mut ffi::csp_packet_t,
+    from_me: c_int,
+) -> c_int {
+    // SAFETY: The router passes the ownership of the packet to the interface.
+    let Some(packet) = (unsafe { Packet::from_raw(packet) }) else {
+        return ErrorCode::Inval.into();
+    };
+    // SAFETY: The trampoline is only used by interfaces created by [register], whose driver
+    // data points to the driver of type D, which lives for the rest of the program lifetime.
+    let driver = unsafe { &*((*iface).driver_data as *const D) };
+    // The packet is dropped during unwinding if the driver panics.
+    match catch_unwind(AssertUnwindSafe(|| {
+        driver.transmit(via, packet, from_me != 0)
+    })) {
+        Ok(Ok(())) => 0,
+        Ok(Err(e)) => e.code().into(),
+        Err(_) => ErrorCode::Driver.into(),
+    }
+}
+
+fn check_name(name: &str) -> Result<CString, CspError> {
+    let invalid = CspError::new("csp_iflist_add", ErrorCode::Inval);
+    if name.is_empty() || name.len() > ffi::CSP_IFLIST_NAME_MAX as usize {
+        return Err(invalid);
+    }
+    CString::new(name).map_err(|_| invalid)
+}
+
+/// Check the name of a new interface. Returns [ErrorCode::Already] if an interface with the
+/// same name exists.
+pub(crate) fn new_iface_name(op: &'static str, name: &str) -> Result<CString, CspError> {
+    let name = check_name(name)?;
+    // SAFETY: FFI call.
+    if !unsafe { ffi::iflist::csp_iflist_get_by_name(name.as_ptr()) }.is_null() {
+        return Err(CspError::new(op, ErrorCode::Already));
+    }
+    Ok(name)
+}
+
+/// Apply the subnet configuration to an interface which a C driver added to the interface list
+/// itself.
+///
+/// The drivers zero the interface, so it is not used for a subnet or as the default interface
+/// yet. It is removed from the list while the configuration is changed and added again
+/// afterwards, so it is only found with the complete configuration.
+///
+/// # Safety
+///
+/// The interface must have been added by the driver and must not be used by any route.
+#[cfg(any(feature = "can", feature = "zmq"))]
+pub(crate) unsafe fn configure_added_iface(
+    op: &'static str,
+    iface: *mut ffi::csp_iface_t,
+    netmask: u16,
+    is_default: bool,
+) -> Result<(), CspError> {
+    if netmask == 0 && !is_default {
+        return Ok(());
+    }
+    // SAFETY: FFI calls with an interface which is valid for the program lifetime. It is not
+    // in the interface list while its configuration is written.
+    unsafe {
+        ffi::iflist::csp_iflist_remove(iface);
+        (*iface).netmask = netmask;
+        (*iface).is_default = is_default as u8;
+        CspError::check(op, ffi::iflist::csp_iflist_add(iface))
+    }
+}
+
+/// Add an interface which is implemented by the given driver to the interface list.
+///
+/// `netmask` is the number of bits of the subnet mask. Returns [ErrorCode::Inval] if the name
+/// is empty, longer than [ffi::CSP_IFLIST_NAME_MAX] or contains a NUL character, and
+/// [ErrorCode::Already] if an interface with the same name exists.
+pub fn register<D: CspInterfaceDriver>(
+    _stack: &CspStack,
+    name: &str,
+    addr: u16,
+    netmask: u16,
+    is_default: bool,
+    driver: D,
+) -> Result<RxHandle, CspError> {
+    let name = new_iface_name("csp_iflist_add", name)?;
+    let interface = Box::into_raw(Box::new(Interface {
+        iface: ffi::csp_iface_t {
+            addr,
+            netmask,
+            name: name.as_ptr(),
+            nexthop: Some(nexthop::<D>),
+            is_default: is_default as u8,
+            ..Default::default()
+        },
+        _name: name,
+        driver,
+    }));
+    // SAFETY: The interface was just allocated. The driver data points into the same
+    // allocation, which is never moved.
+    let iface = unsafe {
+        (*interface).iface.driver_data = &mut (*interface).driver as *mut D as *mut _;
+        &mut (*interface).iface as *mut ffi::csp_iface_t
+    };
+    // SAFETY: FFI call. The interface is valid for the rest of the program lifetime if it was
+    // added.
+    let result = unsafe { ffi::iflist::csp_iflist_add(iface) };
+    if let Err(e) = CspError::check("csp_iflist_add", result) {
+        // SAFETY: The interface was not added, so it is not referenced by libcsp.
+        drop(unsafe { Box::from_raw(interface) });
+        return Err(e);
+    }
+    // SAFETY: The pointer is derived from a box and therefore not null.
+    Ok(RxHandle(unsafe { NonNull::new_unchecked(iface) }))
+}
+
+/// Handle to pass packets received by a [CspInterfaceDriver] to the router.
+#[derive(Debug, Copy, Clone)]
+pub struct RxHandle(NonNull<ffi::csp_iface_t>);
+
+// SAFETY: The interface lives for the rest of the program lifetime and libcsp accepts packets
+// from any task.
+unsafe impl Send for RxHandle {}
+// SAFETY: See above.
+unsafe impl Sync for RxHandle {}
+
+impl RxHandle {
+    /// View of the interface, for example to read its statistics.
+    pub fn iface(&self) -> IfaceRef {
+        // The interface pointer is never null.
+        IfaceRef::from_raw(self.0.as_ptr()).unwrap()
+    }
+
+    pub fn as_ptr(&self) -> *mut ffi::csp_iface_t {
+        self.0.as_ptr()
+    }
+
+    /// Rust wrapper for [ffi::csp_qfifo_write]. The CSP header of the packet must be set with
+    /// [Packet::set_id].
+    pub fn receive(&self, packet: Packet) {
+        // SAFETY: FFI call. The ownership of the packet is passed to the router, and the call
+        // is made from task context.
+        unsafe {
+            ffi::csp_qfifo_write(packet.into_raw(), self.0.as_ptr(), core::ptr::null_mut());
+        }
+    }
+
+    /// Copy a received payload into a new packet and pass it to the router.
+    ///
+    /// Returns [ErrorCode::Inval] and counts a receive error if the payload does not fit into a
+    /// packet, and returns [ErrorCode::NoBufs] and counts a dropped packet if no buffer is
+    /// available.
+    pub fn receive_data(&self, id: CspId, data: &[u8]) -> Result<(), CspError> {
+        if data.len() > Packet::CAPACITY {
+            // SAFETY: The interface pointer is valid.
+            unsafe { increment(core::ptr::addr_of_mut!((*self.0.as_ptr()).rx_error)) };
+            return Err(CspError::new("csp_qfifo_write", ErrorCode::Inval));
+        }
+        // SAFETY: FFI call. The packet is a fresh buffer from the buffer pool.
+        let Some(mut packet) = (unsafe { Packet::from_raw(ffi::csp_buffer_get(0)) }) else {
+            // SAFETY: The interface pointer is valid.
+            unsafe { increment(core::ptr::addr_of_mut!((*self.0.as_ptr()).drop)) };
+            return Err(CspError::new("csp_buffer_get", ErrorCode::NoBufs));
+        };
+        packet.set_id(id);
+        packet.set_data(data)?;
+        self.receive(packet);
+        Ok(())
+    }
+
+    /// Count a frame which was discarded by the link layer, for example because of an invalid
+    /// checksum or framing.
+    pub fn frame_error(&self) {
+        // SAFETY: The interface pointer is valid.
+        unsafe { increment(core::ptr::addr_of_mut!((*self.0.as_ptr()).frame)) };
+    }
+}
+
+/// Increment a statistic counter of an interface. The counters are also written by `libcsp`
+/// without synchronization, so volatile accesses are used like for reading them.
+///
+/// # Safety
+///
+/// The counter must be a field of a valid interface.
+unsafe fn increment(counter: *mut u32) {
+    // SAFETY: The counter is valid.
+    unsafe { counter.write_volatile(counter.read_volatile().wrapping_add(1)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interface_name() {
+        assert!(check_name("RUST").is_ok());
+        assert!(check_name("0123456789").is_ok());
+        assert_eq!(check_name("").unwrap_err().code(), ErrorCode::Inval);
+        assert_eq!(
+            check_name("01234567890").unwrap_err().code(),
+            ErrorCode::Inval
+        );
+        assert_eq!(check_name("RU\0ST").unwrap_err().code(), ErrorCode::Inval);
+    }
+}
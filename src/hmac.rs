@@ -0,0 +1,196 @@
+//! HMAC authentication of packets.
+//!
+//! `libcsp` authenticates packets of connections with [crate::ConnectOpts::HMAC] and sockets
+//! with [crate::SocketFlags::HMACREQ] with a HMAC-SHA1 over the packet data, which is
+//! truncated to [HMAC_LENGTH] bytes and appended to the data. The key installed with
+//! [set_key] is not used directly: `libcsp` derives a 16 byte key from the first bytes of the
+//! SHA1 hash of the installed key.
+//!
+//! [HmacKey] implements the same derivation and format in Rust with the `sha1` and `hmac`
+//! crates, so captured packets can be authenticated without the `libcsp` stack, for example by
+//! ground tooling. The module requires the `hmac` feature.
+use core::{
+    fmt::{self, Debug},
+    ptr,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+use ::hmac::{Hmac, Mac};
+use sha1::{Digest, Sha1};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, PoisonError};
+
+use crate::{ffi, CspError, CspStack, ErrorCode, Packet};
+
+/// Length of the truncated HMAC which is appended to the packet data.
+pub const HMAC_LENGTH: usize = ffi::hmac::CSP_HMAC_LENGTH as usize;
+/// Length of the key derived from the installed key.
+pub const KEY_LENGTH: usize = 16;
+
+type HmacSha1 = Hmac<Sha1>;
+
+/// Overwrite secret material with zeros in a way which is not optimized away.
+fn zeroize(buf: &mut [u8]) {
+    for byte in buf.iter_mut() {
+        // SAFETY: Write to a valid reference.
+        unsafe { ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Derived HMAC key, see [set_key]. The key is overwritten with zeros when it is dropped.
+#[derive(Clone)]
+pub struct HmacKey([u8; KEY_LENGTH]);
+
+impl HmacKey {
+    /// Derive the key in the same way as [ffi::hmac::csp_hmac_set_key].
+    pub fn new(key: &[u8]) -> Self {
+        let mut hash = Sha1::digest(key);
+        let mut derived = [0; KEY_LENGTH];
+        derived.copy_from_slice(&hash[..KEY_LENGTH]);
+        zeroize(&mut hash);
+        Self(derived)
+    }
+
+    fn mac(&self, data: &[u8]) -> HmacSha1 {
+        let mut mac = HmacSha1::new_from_slice(&self.0).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        mac
+    }
+
+    /// Truncated HMAC of the data.
+    pub fn tag(&self, data: &[u8]) -> [u8; HMAC_LENGTH] {
+        let mut hmac = self.mac(data).finalize().into_bytes();
+        let mut tag = [0; HMAC_LENGTH];
+        tag.copy_from_slice(&hmac[..HMAC_LENGTH]);
+        zeroize(&mut hmac);
+        tag
+    }
+
+    /// Append the HMAC of the payload to the packet, like [ffi::hmac::csp_hmac_append]
+    /// without the header.
+    ///
+    /// Returns [ErrorCode::NoMem] if the HMAC does not fit into the packet.
+    pub fn append(&self, packet: &mut Packet) -> Result<(), CspError> {
+        if packet.remaining() < HMAC_LENGTH {
+            return Err(CspError::new("csp_hmac_append", ErrorCode::NoMem));
+        }
+        let tag = self.tag(packet);
+        packet.extend_from_slice(&tag)
+    }
+
+    /// Verify the HMAC at the end of the data and return the authenticated data without the
+    /// HMAC. The HMAC is compared in constant time.
+    ///
+    /// Returns [ErrorCode::Hmac] if the data is shorter than the HMAC or the HMAC does not
+    /// match.
+    pub fn verify<'a>(&self, data: &'a [u8]) -> Result<&'a [u8], CspError> {
+        let Some(len) = data.len().checked_sub(HMAC_LENGTH) else {
+            return Err(CspError::new("csp_hmac_verify", ErrorCode::Hmac));
+        };
+        let (data, tag) = data.split_at(len);
+        self.mac(data)
+            .verify_truncated_left(tag)
+            .map_err(|_| CspError::new("csp_hmac_verify", ErrorCode::Hmac))?;
+        Ok(data)
+    }
+
+    /// Verify the HMAC at the end of the payload and remove it from the packet, like
+    /// [ffi::hmac::csp_hmac_verify] without the header. The packet is left unchanged if the
+    /// verification fails.
+    pub fn verify_packet(&self, packet: &mut Packet) -> Result<(), CspError> {
+        let len = self.verify(packet)?.len();
+        packet.set_len(len)
+    }
+}
+
+impl Debug for HmacKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HmacKey").finish_non_exhaustive()
+    }
+}
+
+impl Drop for HmacKey {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+#[cfg(feature = "std")]
+static KEY: Mutex<Option<HmacKey>> = Mutex::new(None);
+
+/// Rust wrapper for [ffi::hmac::csp_hmac_set_key]. `libcsp` overwrites the previously
+/// installed key, and with the `std` feature, a copy of the derived key is kept for [key]. The
+/// copy of the previous key is overwritten with zeros.
+///
+/// Returns [ErrorCode::Inval] if the key is larger than 4 GiB.
+pub fn set_key(_stack: &CspStack, key: &[u8]) -> Result<(), CspError> {
+    let Ok(keylen) = u32::try_from(key.len()) else {
+        return Err(CspError::new("csp_hmac_set_key", ErrorCode::Inval));
+    };
+    #[cfg(feature = "std")]
+    let mut installed = KEY.lock().unwrap_or_else(PoisonError::into_inner);
+    // SAFETY: FFI call. The key is valid for its length, and libcsp only keeps the derived
+    // key.
+    let result = unsafe { ffi::hmac::csp_hmac_set_key(key.as_ptr().cast(), keylen) };
+    CspError::check("csp_hmac_set_key", result)?;
+    #[cfg(feature = "std")]
+    {
+        *installed = Some(HmacKey::new(key));
+    }
+    Ok(())
+}
+
+/// Copy of the key installed with [set_key], to authenticate packets in Rust.
+#[cfg(feature = "std")]
+pub fn key(_stack: &CspStack) -> Option<HmacKey> {
+    KEY.lock().unwrap_or_else(PoisonError::into_inner).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(data: &[u8]) -> std::string::String {
+        data.iter().map(|b| std::format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn key_derivation() {
+        let key = HmacKey::new(b"abc");
+        // SHA1 of "abc" from RFC 3174.
+        assert_eq!(hex(&key.0), "a9993e364706816aba3e25717850c26c");
+        assert_eq!(std::format!("{key:?}"), "HmacKey { .. }");
+    }
+
+    #[test]
+    fn known_tags() {
+        // Computed with Python's hashlib and hmac modules in the same way as csp_hmac.c: the
+        // derived key is the first 16 bytes of the SHA1 of the installed key, and the tag is
+        // the first 4 bytes of the HMAC-SHA1 of the data with the derived key.
+        let key = HmacKey::new(b"libcsp");
+        assert_eq!(hex(&key.0), "2087110604045482e74ac4619a6162a5");
+        assert_eq!(hex(&key.tag(b"Hello World")), "390e8955");
+        assert_eq!(hex(&key.tag(b"")), "2318adec");
+    }
+
+    #[test]
+    fn verify() {
+        let key = HmacKey::new(b"secret");
+        let mut data = std::vec::Vec::from(*b"payload");
+        data.extend_from_slice(&key.tag(b"payload"));
+        assert_eq!(key.verify(&data), Ok(&b"payload"[..]));
+        assert_eq!(
+            HmacKey::new(b"other").verify(&data).unwrap_err().code(),
+            ErrorCode::Hmac
+        );
+        data[0] ^= 1;
+        assert_eq!(key.verify(&data).unwrap_err().code(), ErrorCode::Hmac);
+        assert_eq!(
+            key.verify(&data[..HMAC_LENGTH - 1]).unwrap_err().code(),
+            ErrorCode::Hmac
+        );
+        let tag = key.tag(b"");
+        assert_eq!(key.verify(&tag), Ok(&[][..]));
+    }
+}
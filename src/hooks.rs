@@ -0,0 +1,387 @@
+//! Rust implementation of the hook functions required by `libcsp`.
+//!
+//! `libcsp` expects the application to provide the hook symbols listed in [ffi::hooks]. With the
+//! `hooks` feature, this module defines all of them exactly once and forwards every call to the
+//! installed [Hooks] implementation. Applications which enable this feature must not define any
+//! of these symbols themselves.
+//!
+//! All methods of [Hooks] have default implementations, which are sensible defaults for Linux.
+//! [DefaultHooks] is used until another implementation is installed with [install]. Packets
+//! passing the input and output hooks are passed to the observers of the [crate::tap].
+//!
+//! Panics are caught at the FFI boundary. If the installed implementation panics, the hook
+//! reports an error or a neutral value to `libcsp`, and the default implementation is not run.
+use core::{
+    ffi::{c_int, c_uint},
+    mem::ManuallyDrop,
+    ptr::NonNull,
+    time::Duration,
+};
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Arc, PoisonError, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    ffi, iface_name,
+    tap::{self, Direction, TapEvent},
+    CspError, CspId, ErrorCode, Packet,
+};
+
+/// Implementation of the `libcsp` hooks.
+///
+/// The hooks are invoked from `libcsp` tasks, for example the router task, and should return
+/// quickly.
+pub trait Hooks: Send + Sync {
+    /// Reboot the system. The default reboots Linux systems, which requires the
+    /// `CAP_SYS_BOOT` capability.
+    fn reboot(&self) {
+        #[cfg(target_os = "linux")]
+        // SAFETY: FFI calls.
+        unsafe {
+            libc::sync();
+            libc::reboot(libc::RB_AUTOBOOT);
+        }
+    }
+
+    /// Shut down the system. The default powers off Linux systems, which requires the
+    /// `CAP_SYS_BOOT` capability.
+    fn shutdown(&self) {
+        #[cfg(target_os = "linux")]
+        // SAFETY: FFI calls.
+        unsafe {
+            libc::sync();
+            libc::reboot(libc::RB_POWER_OFF);
+        }
+    }
+
+    /// Free memory in bytes. The default returns the free RAM on Linux systems and 0 otherwise.
+    fn memfree(&self) -> u32 {
+        #[cfg(target_os = "linux")]
+        {
+            // SAFETY: All fields of the structure are plain integers.
+            let mut info: libc::sysinfo = unsafe { core::mem::zeroed() };
+            // SAFETY: FFI call.
+            if unsafe { libc::sysinfo(&mut info) } == 0 {
+                let free = (info.freeram as u64).saturating_mul(info.mem_unit.into());
+                return free.try_into().unwrap_or(u32::MAX);
+            }
+        }
+        0
+    }
+
+    /// Write the process list into the packet. The default lists the threads of the current
+    /// process on Linux systems.
+    fn ps(&self, packet: &mut Packet) {
+        let Ok(tasks) = std::fs::read_dir("/proc/self/task") else {
+            return;
+        };
+        for task in tasks.flatten() {
+            let Ok(name) = std::fs::read_to_string(task.path().join("comm")) else {
+                continue;
+            };
+            let tid = task.file_name();
+            let line = std::format!("{} {}\n", tid.to_string_lossy(), name.trim_end());
+            if packet.extend_from_slice(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Current system time. The default uses the system clock.
+    fn clock_get(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    /// Set the system time. The default sets the realtime clock on Unix systems, which requires
+    /// the `CAP_SYS_TIME` capability.
+    fn clock_set(&self, _time: Duration) -> Result<(), CspError> {
+        #[cfg(unix)]
+        {
+            let time = libc::timespec {
+                tv_sec: _time.as_secs() as libc::time_t,
+                tv_nsec: _time.subsec_nanos() as _,
+            };
+            // SAFETY: FFI call.
+            if unsafe { libc::clock_settime(libc::CLOCK_REALTIME, &time) } == 0 {
+                return Ok(());
+            }
+            Err(CspError::new("csp_clock_set_time", ErrorCode::Inval))
+        }
+        #[cfg(not(unix))]
+        Err(CspError::new("csp_clock_set_time", ErrorCode::NotSup))
+    }
+
+    /// Encrypt a message for the TUN interface. Returns the length of the ciphertext. With the
+    /// `crypto` feature, the default uses the `crypto::CryptoProvider` installed with
+    /// `crypto::install`. It returns [ErrorCode::NotSup] if there is none or if the feature is
+    /// disabled.
+    fn encrypt(&self, _msg: &[u8], _ciphertext: &mut [u8]) -> Result<usize, CspError> {
+        #[cfg(feature = "crypto")]
+        return crate::crypto::encrypt(_msg, _ciphertext);
+        #[cfg(not(feature = "crypto"))]
+        Err(CspError::new("csp_crypto_encrypt", ErrorCode::NotSup))
+    }
+
+    /// Decrypt a message received on the TUN interface. Returns the length of the message. With
+    /// the `crypto` feature, the default uses the `crypto::CryptoProvider` installed with
+    /// `crypto::install`. It returns [ErrorCode::NotSup] if there is none or if the feature is
+    /// disabled.
+    fn decrypt(&self, _ciphertext: &[u8], _msg: &mut [u8]) -> Result<usize, CspError> {
+        #[cfg(feature = "crypto")]
+        return crate::crypto::decrypt(_ciphertext, _msg);
+        #[cfg(not(feature = "crypto"))]
+        Err(CspError::new("csp_crypto_decrypt", ErrorCode::NotSup))
+    }
+}
+
+/// [Hooks] implementation which only uses the default implementations.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct DefaultHooks;
+
+impl Hooks for DefaultHooks {}
+
+static HOOKS: RwLock<Option<Arc<dyn Hooks>>> = RwLock::new(None);
+
+/// Install the hooks implementation. Returns the previously installed implementation.
+pub fn install(hooks: impl Hooks + 'static) -> Option<Arc<dyn Hooks>> {
+    HOOKS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(Arc::new(hooks))
+}
+
+/// Remove the installed hooks implementation, so [DefaultHooks] is used again.
+pub fn uninstall() -> Option<Arc<dyn Hooks>> {
+    HOOKS.write().unwrap_or_else(PoisonError::into_inner).take()
+}
+
+/// Invoke the installed hooks, or [DefaultHooks] if there are none. The lock is released
+/// before the call, so the hooks may install another implementation. If the hooks panic,
+/// `fallback` is returned, so no panic unwinds into `libcsp`.
+fn with_hooks<R>(fallback: R, f: impl FnOnce(&dyn Hooks) -> R) -> R {
+    let hooks = HOOKS.read().unwrap_or_else(PoisonError::into_inner).clone();
+    catch_unwind(AssertUnwindSafe(|| match hooks.as_deref() {
+        Some(hooks) => f(hooks),
+        None => f(&DefaultHooks),
+    }))
+    .unwrap_or(fallback)
+}
+
+/// Borrow a packet owned by `libcsp` for the duration of a hook call.
+fn with_packet<R>(packet: *mut ffi::csp_packet_t, f: impl FnOnce(&mut Packet) -> R) -> Option<R> {
+    // libcsp passes a valid packet. The packet is never dropped, also not during unwinding,
+    // because the ownership stays with libcsp.
+    let mut packet = ManuallyDrop::new(Packet(NonNull::new(packet)?));
+    Some(f(&mut packet))
+}
+
+/// Pass a packet to the tap observers. Panics of the observers are caught at the FFI boundary.
+fn notify_tap(event: &TapEvent) {
+    let _ = catch_unwind(|| tap::notify(event));
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_output_hook(
+    idout: *mut ffi::csp_id_t,
+    packet: *mut ffi::csp_packet_t,
+    iface: *mut ffi::csp_iface_t,
+    via: u16,
+    from_me: c_int,
+) {
+    if idout.is_null() {
+        return;
+    }
+    // SAFETY: libcsp passes a valid identifier.
+    let id = CspId::from(unsafe { *idout });
+    with_packet(packet, |packet| {
+        notify_tap(&TapEvent {
+            direction: Direction::Outbound,
+            id,
+            // SAFETY: libcsp passes a valid interface.
+            iface: unsafe { iface_name(iface) },
+            via: Some(via),
+            from_me: from_me != 0,
+            data: packet,
+        });
+    });
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_input_hook(iface: *mut ffi::csp_iface_t, packet: *mut ffi::csp_packet_t) {
+    with_packet(packet, |packet| {
+        notify_tap(&TapEvent {
+            direction: Direction::Inbound,
+            id: packet.id(),
+            // SAFETY: libcsp passes a valid interface.
+            iface: unsafe { iface_name(iface) },
+            via: None,
+            from_me: false,
+            data: packet,
+        });
+    });
+}
+
+#[no_mangle]
+extern "C" fn csp_reboot_hook() {
+    with_hooks((), |hooks| hooks.reboot())
+}
+
+#[no_mangle]
+extern "C" fn csp_shutdown_hook() {
+    with_hooks((), |hooks| hooks.shutdown())
+}
+
+#[no_mangle]
+extern "C" fn csp_memfree_hook() -> u32 {
+    with_hooks(0, |hooks| hooks.memfree())
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_ps_hook(packet: *mut ffi::csp_packet_t) -> c_uint {
+    with_packet(packet, |packet| {
+        packet.clear();
+        // A panicking implementation may leave partial output.
+        if !with_hooks(false, |hooks| {
+            hooks.ps(packet);
+            true
+        }) {
+            packet.clear();
+        }
+        if packet.remaining() > 0 {
+            // Can not fail, there is space left.
+            let _ = packet.extend_from_slice(&[0]);
+        }
+        packet.len() as c_uint
+    })
+    .unwrap_or(0)
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_clock_get_time(time: *mut ffi::csp_timestamp_t) {
+    if time.is_null() {
+        return;
+    }
+    let now = with_hooks(Duration::ZERO, |hooks| hooks.clock_get());
+    // SAFETY: libcsp passes a valid timestamp.
+    unsafe {
+        *time = ffi::csp_timestamp_t {
+            tv_sec: now.as_secs() as u32,
+            tv_nsec: now.subsec_nanos(),
+        }
+    };
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_clock_set_time(time: *const ffi::csp_timestamp_t) -> c_int {
+    // SAFETY: libcsp passes a valid timestamp.
+    let Some(time) = (unsafe { time.as_ref() }) else {
+        return ErrorCode::Inval.into();
+    };
+    let time = Duration::new(time.tv_sec.into(), time.tv_nsec);
+    let failed = Err(CspError::new("csp_clock_set_time", ErrorCode::Inval));
+    match with_hooks(failed, |hooks| hooks.clock_set(time)) {
+        Ok(()) => 0,
+        Err(e) => e.code().into(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_crypto_encrypt(
+    msg_begin: *mut u8,
+    msg_len: u8,
+    ciphertext_out: *mut u8,
+) -> c_int {
+    if msg_begin.is_null() || ciphertext_out.is_null() {
+        return ErrorCode::Inval.into();
+    }
+    // SAFETY: libcsp passes a message with the given length and a ciphertext buffer which is
+    // large enough for a full packet.
+    let (msg, ciphertext) = unsafe {
+        (
+            core::slice::from_raw_parts(msg_begin, msg_len.into()),
+            core::slice::from_raw_parts_mut(ciphertext_out, ffi::CSP_BUFFER_SIZE),
+        )
+    };
+    let failed = Err(CspError::new("csp_crypto_encrypt", ErrorCode::Inval));
+    match with_hooks(failed, |hooks| hooks.encrypt(msg, ciphertext)) {
+        Ok(len) => len as c_int,
+        Err(e) => e.code().into(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_crypto_decrypt(
+    ciphertext_in: *mut u8,
+    ciphertext_len: u8,
+    msg_out: *mut u8,
+) -> c_int {
+    if ciphertext_in.is_null() || msg_out.is_null() {
+        return ErrorCode::Inval.into();
+    }
+    // SAFETY: libcsp passes a ciphertext with the given length and a message buffer which is
+    // large enough for a full packet.
+    let (ciphertext, msg) = unsafe {
+        (
+            core::slice::from_raw_parts(ciphertext_in, ciphertext_len.into()),
+            core::slice::from_raw_parts_mut(msg_out, ffi::CSP_BUFFER_SIZE),
+        )
+    };
+    let failed = Err(CspError::new("csp_crypto_decrypt", ErrorCode::Inval));
+    match with_hooks(failed, |hooks| hooks.decrypt(ciphertext, msg)) {
+        Ok(len) => len as c_int,
+        Err(e) => e.code().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    struct Counting(Arc<AtomicU32>);
+
+    impl Hooks for Counting {
+        fn reboot(&self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            panic!("reboot failed");
+        }
+
+        fn memfree(&self) -> u32 {
+            // Replacing the hooks from within a hook must not deadlock.
+            install(DefaultHooks);
+            42
+        }
+
+        fn clock_set(&self, _time: Duration) -> Result<(), CspError> {
+            panic!("clock_set failed");
+        }
+    }
+
+    #[test]
+    fn dispatch() {
+        let calls = Arc::new(AtomicU32::new(0));
+        install(Counting(calls.clone()));
+        // The panic is caught and the default reboot is not run.
+        with_hooks((), |hooks| hooks.reboot());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        let failed = Err(CspError::new("csp_clock_set_time", ErrorCode::Inval));
+        assert_eq!(
+            with_hooks(failed, |hooks| hooks.clock_set(Duration::ZERO))
+                .unwrap_err()
+                .code(),
+            ErrorCode::Inval
+        );
+        assert_eq!(with_hooks(0, |hooks| hooks.memfree()), 42);
+        // The hook installed the defaults.
+        assert_ne!(
+            with_hooks(Duration::ZERO, |hooks| hooks.clock_get()),
+            Duration::ZERO
+        );
+        assert!(uninstall().is_some());
+        assert!(uninstall().is_none());
+    }
+}
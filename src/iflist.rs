@@ -0,0 +1,232 @@
+//! Interface list of the CSP stack.
+//!
+//! Interfaces which were added with [csp_iflist_add] can be inspected with [iter] and the
+//! lookup functions, which return an [IfaceRef] view of the interface. `libcsp` requires that
+//! interfaces stay valid for the whole program lifetime after they were added.
+use core::{ffi::CStr, fmt, ptr::NonNull};
+
+use crate::{ffi, iface_name, CspError, CspInterface, CspStack};
+
+/// Rust wrapper for [ffi::iflist::csp_iflist_print].
+pub fn csp_iflist_print() {
+    // SAFETY: FFI call.
+    unsafe { ffi::iflist::csp_iflist_print() }
+}
+
+/// Rust wrapper for [ffi::iflist::csp_iflist_add]. The interface stays in the list for the rest
+/// of the program lifetime.
+pub fn csp_iflist_add(iface: &'static mut CspInterface) -> Result<(), CspError> {
+    // SAFETY: FFI call. The interface is valid for the rest of the program lifetime.
+    CspError::check("csp_iflist_add", unsafe {
+        ffi::iflist::csp_iflist_add(&mut iface.0)
+    })
+}
+
+/// Snapshot of the statistic counters of an interface.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct IfaceStats {
+    /// Successfully transmitted packets.
+    pub tx: u32,
+    /// Successfully received packets.
+    pub rx: u32,
+    /// Transmit errors.
+    pub tx_error: u32,
+    /// Receive errors, for example too large messages.
+    pub rx_error: u32,
+    /// Dropped packets.
+    pub drop: u32,
+    /// Authentication errors.
+    pub autherr: u32,
+    /// Frame format errors.
+    pub frame: u32,
+    /// Transmitted bytes.
+    pub txbytes: u32,
+    /// Received bytes.
+    pub rxbytes: u32,
+    /// Interrupts.
+    pub irq: u32,
+}
+
+/// View of an interface in the interface list.
+#[derive(Copy, Clone)]
+pub struct IfaceRef(NonNull<ffi::csp_iface_t>);
+
+// SAFETY: The interface is only read, and the counters are read with volatile reads.
+unsafe impl Send for IfaceRef {}
+// SAFETY: See above.
+unsafe impl Sync for IfaceRef {}
+
+impl IfaceRef {
+    pub(crate) fn from_raw(iface: *mut ffi::csp_iface_t) -> Option<Self> {
+        NonNull::new(iface).map(Self)
+    }
+
+    fn inner(&self) -> &ffi::csp_iface_t {
+        // SAFETY: Interfaces in the interface list are valid for the program lifetime.
+        unsafe { self.0.as_ref() }
+    }
+
+    pub fn as_ptr(&self) -> *mut ffi::csp_iface_t {
+        self.0.as_ptr()
+    }
+
+    /// Name of the interface. Empty if the interface has no valid name.
+    pub fn name(&self) -> &str {
+        // SAFETY: The interface pointer is valid.
+        unsafe { iface_name(self.0.as_ptr()) }
+    }
+
+    /// Host address on the subnet of the interface.
+    pub fn addr(&self) -> u16 {
+        self.inner().addr
+    }
+
+    /// Number of bits of the subnet mask.
+    pub fn netmask(&self) -> u16 {
+        self.inner().netmask
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.inner().is_default != 0
+    }
+
+    /// Snapshot of the statistic counters. The counters are updated concurrently by the
+    /// interface drivers, so the snapshot is not necessarily consistent across counters.
+    pub fn stats(&self) -> IfaceStats {
+        let iface = self.0.as_ptr();
+        // SAFETY: The interface pointer is valid. Volatile reads are used because the
+        // counters are written by other tasks.
+        unsafe {
+            IfaceStats {
+                tx: core::ptr::addr_of!((*iface).tx).read_volatile(),
+                rx: core::ptr::addr_of!((*iface).rx).read_volatile(),
+                tx_error: core::ptr::addr_of!((*iface).tx_error).read_volatile(),
+                rx_error: core::ptr::addr_of!((*iface).rx_error).read_volatile(),
+                drop: core::ptr::addr_of!((*iface).drop).read_volatile(),
+                autherr: core::ptr::addr_of!((*iface).autherr).read_volatile(),
+                frame: core::ptr::addr_of!((*iface).frame).read_volatile(),
+                txbytes: core::ptr::addr_of!((*iface).txbytes).read_volatile(),
+                rxbytes: core::ptr::addr_of!((*iface).rxbytes).read_volatile(),
+                irq: core::ptr::addr_of!((*iface).irq).read_volatile(),
+            }
+        }
+    }
+}
+
+impl fmt::Debug for IfaceRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IfaceRef")
+            .field("name", &self.name())
+            .field("addr", &self.addr())
+            .field("netmask", &self.netmask())
+            .field("is_default", &self.is_default())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// Iterator over the interface list, created by [iter].
+pub struct Iter {
+    next: Option<IfaceRef>,
+}
+
+impl Iterator for Iter {
+    type Item = IfaceRef;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let iface = self.next?;
+        self.next = IfaceRef::from_raw(iface.inner().next);
+        Some(iface)
+    }
+}
+
+/// Iterator over all interfaces in the interface list.
+pub fn iter(_stack: &CspStack) -> Iter {
+    // SAFETY: FFI call.
+    Iter {
+        next: IfaceRef::from_raw(unsafe { ffi::iflist::csp_iflist_get() }),
+    }
+}
+
+/// Rust wrapper for [ffi::iflist::csp_iflist_get_by_name].
+pub fn get_by_name(_stack: &CspStack, name: &CStr) -> Option<IfaceRef> {
+    // SAFETY: FFI call.
+    IfaceRef::from_raw(unsafe { ffi::iflist::csp_iflist_get_by_name(name.as_ptr()) })
+}
+
+/// Rust wrapper for [ffi::iflist::csp_iflist_get_by_addr].
+pub fn get_by_addr(_stack: &CspStack, addr: u16) -> Option<IfaceRef> {
+    // SAFETY: FFI call.
+    IfaceRef::from_raw(unsafe { ffi::iflist::csp_iflist_get_by_addr(addr) })
+}
+
+/// Rust wrapper for [ffi::iflist::csp_iflist_get_by_subnet]. Returns the first interface whose
+/// subnet contains the address.
+pub fn get_by_subnet(_stack: &CspStack, addr: u16) -> Option<IfaceRef> {
+    // SAFETY: FFI call.
+    IfaceRef::from_raw(unsafe {
+        ffi::iflist::csp_iflist_get_by_subnet(addr, core::ptr::null_mut())
+    })
+}
+
+/// Rust wrapper for [ffi::iflist::csp_iflist_get_by_index].
+pub fn get_by_index(_stack: &CspStack, index: usize) -> Option<IfaceRef> {
+    let index = index.try_into().ok()?;
+    // SAFETY: FFI call.
+    IfaceRef::from_raw(unsafe { ffi::iflist::csp_iflist_get_by_index(index) })
+}
+
+/// Rust wrapper for [ffi::iflist::csp_iflist_remove]. Routes which use the interface are not
+/// removed.
+pub fn remove(_stack: &CspStack, iface: IfaceRef) {
+    // SAFETY: FFI call.
+    unsafe { ffi::iflist::csp_iflist_remove(iface.as_ptr()) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{boxed::Box, format, vec::Vec};
+
+    #[test]
+    fn iface_view() {
+        let mut iface = CspInterface::new(12, true);
+        iface.0.name = c"RUST".as_ptr();
+        iface.0.netmask = 8;
+        iface.0.tx = 3;
+        iface.0.rx_error = 1;
+        iface.0.irq = 7;
+        let iface = IfaceRef::from_raw(&mut iface.0).unwrap();
+        assert_eq!(iface.name(), "RUST");
+        assert_eq!(iface.addr(), 12);
+        assert_eq!(iface.netmask(), 8);
+        assert!(iface.is_default());
+        assert_eq!(
+            iface.stats(),
+            IfaceStats {
+                tx: 3,
+                rx_error: 1,
+                irq: 7,
+                ..Default::default()
+            }
+        );
+        assert!(format!("{iface:?}").contains("name: \"RUST\""));
+        assert!(IfaceRef::from_raw(core::ptr::null_mut()).is_none());
+    }
+
+    #[test]
+    fn iterate_list() {
+        let mut last = Box::new(CspInterface::new(2, false));
+        let mut first = Box::new(CspInterface::new(1, false));
+        first.0.next = &mut last.0;
+        let iter = Iter {
+            next: IfaceRef::from_raw(&mut first.0),
+        };
+        let ifaces: Vec<_> = iter.map(|iface| (iface.as_ptr(), iface.addr())).collect();
+        assert_eq!(
+            ifaces,
+            [(&mut first.0 as *mut _, 1), (&mut last.0 as *mut _, 2)]
+        );
+        assert_eq!(Iter { next: None }.count(), 0);
+    }
+}
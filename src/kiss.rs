@@ -0,0 +1,331 @@
+//! KISS interface over arbitrary byte streams.
+//!
+//! [KissInterface] adds a `libcsp` KISS interface whose frames are written to and read from a
+//! byte stream, for example a serial port, a pseudo-terminal or a TCP stream. The received
+//! bytes are passed to [ffi::kiss::csp_kiss_rx] by a dedicated RX thread, which runs until the
+//! stream reports the end of the file or an error.
+//!
+//! The KISS interface of `libcsp` serializes the frames of concurrent senders with the
+//! `csp_usart_lock` and `csp_usart_unlock` functions, which are usually provided by the C
+//! USART drivers. The `kiss` feature defines both symbols for the interfaces of this module, so
+//! the C USART drivers can not be linked at the same time.
+use core::ffi::{c_int, c_void};
+use std::{
+    boxed::Box,
+    ffi::CString,
+    format,
+    io::{self, Read, Write},
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    thread::{self, JoinHandle},
+};
+
+use crate::{driver::new_iface_name, ffi, iflist::IfaceRef, CspError, CspStack, ErrorCode};
+
+/// Size of the buffer used by the RX thread to read from the stream.
+const RX_BUF_SIZE: usize = 256;
+
+/// Transmit side of a KISS interface, referenced by the driver data of the interface.
+struct TxDriver {
+    writer: Mutex<Box<dyn Write + Send>>,
+    locked: Mutex<bool>,
+    unlocked: Condvar,
+}
+
+impl TxDriver {
+    fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            locked: Mutex::new(false),
+            unlocked: Condvar::new(),
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .write_all(data)
+    }
+
+    /// Lock the stream for the frame of one sender.
+    fn lock(&self) {
+        let mut locked = self.locked.lock().unwrap_or_else(PoisonError::into_inner);
+        while *locked {
+            locked = self
+                .unlocked
+                .wait(locked)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *locked = true;
+    }
+
+    /// Flush the frame and unlock the stream.
+    fn unlock(&self) {
+        // A failed flush is detected by the next write.
+        let _ = self
+            .writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .flush();
+        *self.locked.lock().unwrap_or_else(PoisonError::into_inner) = false;
+        self.unlocked.notify_one();
+    }
+}
+
+struct Interface {
+    iface: ffi::csp_iface_t,
+    ifdata: ffi::kiss::csp_kiss_interface_data_t,
+    // Referenced by the interface.
+    _name: CString,
+    driver: TxDriver,
+}
+
+unsafe extern "C" fn tx(driver_data: *mut c_void, data: *const u8, len: usize) -> c_int {
+    if driver_data.is_null() || data.is_null() {
+        return ErrorCode::Inval.into();
+    }
+    // SAFETY: The driver data of the interfaces created by this module points to a TxDriver,
+    // and libcsp passes data with the given length.
+    let (driver, data) = unsafe {
+        (
+            &*(driver_data as *const TxDriver),
+            core::slice::from_raw_parts(data, len),
+        )
+    };
+    match catch_unwind(AssertUnwindSafe(|| driver.write(data))) {
+        Ok(Ok(())) => 0,
+        _ => ErrorCode::Driver.into(),
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_usart_lock(driver_data: *mut c_void) {
+    // SAFETY: Only the KISS interfaces created by this module use the USART lock.
+    if let Some(driver) = unsafe { (driver_data as *const TxDriver).as_ref() } {
+        driver.lock();
+    }
+}
+
+#[no_mangle]
+unsafe extern "C" fn csp_usart_unlock(driver_data: *mut c_void) {
+    // SAFETY: Only the KISS interfaces created by this module use the USART lock.
+    if let Some(driver) = unsafe { (driver_data as *const TxDriver).as_ref() } {
+        driver.unlock();
+    }
+}
+
+/// Stream which is shared between the RX thread and the transmit side.
+struct Shared<S>(Arc<S>);
+
+impl<S> Read for Shared<S>
+where
+    for<'a> &'a S: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self.0).read(buf)
+    }
+}
+
+impl<S> Write for Shared<S>
+where
+    for<'a> &'a S: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.0).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.0).flush()
+    }
+}
+
+fn rx_loop(iface: IfaceRef, mut reader: impl Read) -> io::Result<()> {
+    let mut buf = [0; RX_BUF_SIZE];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            // SAFETY: FFI call. The interface is a KISS interface, and the call is made from
+            // task context.
+            Ok(len) => unsafe {
+                ffi::kiss::csp_kiss_rx(iface.as_ptr(), buf.as_ptr(), len, core::ptr::null_mut())
+            },
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// KISS interface on top of a byte stream.
+///
+/// The interface stays in the interface list for the rest of the program lifetime. Dropping
+/// this handle detaches the RX thread.
+#[derive(Debug)]
+pub struct KissInterface {
+    iface: IfaceRef,
+    rx_thread: JoinHandle<io::Result<()>>,
+}
+
+impl KissInterface {
+    /// Add a KISS interface which uses the given stream for both directions. Streams like
+    /// [std::fs::File], [std::net::TcpStream] and [std::os::unix::net::UnixStream] can be
+    /// read and written through shared references, which allows reading and writing
+    /// concurrently.
+    ///
+    /// See [Self::new_split] for the returned errors.
+    pub fn new<S>(
+        stack: &CspStack,
+        name: &str,
+        addr: u16,
+        netmask: u16,
+        is_default: bool,
+        stream: S,
+    ) -> Result<Self, CspError>
+    where
+        S: Send + Sync + 'static,
+        for<'a> &'a S: Read + Write,
+    {
+        let stream = Arc::new(stream);
+        Self::new_split(
+            stack,
+            name,
+            addr,
+            netmask,
+            is_default,
+            Shared(stream.clone()),
+            Shared(stream),
+        )
+    }
+
+    /// Add a KISS interface which reads received frames from `reader` and writes transmitted
+    /// frames to `writer`.
+    ///
+    /// `netmask` is the number of bits of the subnet mask. Returns [ErrorCode::Inval] if the name
+    /// is empty, longer than [ffi::CSP_IFLIST_NAME_MAX] or contains a NUL character,
+    /// [ErrorCode::Already] if an interface with the same name exists, and [ErrorCode::NoMem]
+    /// if the RX thread could not be spawned. The interface is added in the last case.
+    pub fn new_split(
+        _stack: &CspStack,
+        name: &str,
+        addr: u16,
+        netmask: u16,
+        is_default: bool,
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Result<Self, CspError> {
+        let thread_name = format!("csp-kiss-{}", name);
+        let name = new_iface_name("csp_kiss_add_interface", name)?;
+        let interface = Box::into_raw(Box::new(Interface {
+            iface: ffi::csp_iface_t {
+                addr,
+                netmask,
+                name: name.as_ptr(),
+                is_default: is_default as u8,
+                ..Default::default()
+            },
+            ifdata: ffi::kiss::csp_kiss_interface_data_t {
+                tx_func: Some(tx),
+                ..Default::default()
+            },
+            _name: name,
+            driver: TxDriver::new(writer),
+        }));
+        // SAFETY: The interface was just allocated. The interface and driver data point into
+        // the same allocation, which is never moved.
+        let iface = unsafe {
+            (*interface).iface.interface_data = &mut (*interface).ifdata as *mut _ as *mut _;
+            (*interface).iface.driver_data = &mut (*interface).driver as *mut _ as *mut _;
+            &mut (*interface).iface as *mut ffi::csp_iface_t
+        };
+        // SAFETY: FFI call. The interface is valid for the rest of the program lifetime if it
+        // was added.
+        let result = unsafe { ffi::kiss::csp_kiss_add_interface(iface) };
+        if let Err(e) = CspError::check("csp_kiss_add_interface", result) {
+            // SAFETY: The interface was not added, so it is not referenced by libcsp.
+            drop(unsafe { Box::from_raw(interface) });
+            return Err(e);
+        }
+        // The pointer is derived from a box and therefore not null.
+        let iface = IfaceRef::from_raw(iface).unwrap();
+        let rx_thread = thread::Builder::new()
+            .name(thread_name)
+            .spawn(move || rx_loop(iface, reader))
+            .map_err(|_| CspError::new("csp_kiss_add_interface", ErrorCode::NoMem))?;
+        Ok(Self { iface, rx_thread })
+    }
+
+    /// View of the interface, for example to read its statistics.
+    pub fn iface(&self) -> IfaceRef {
+        self.iface
+    }
+
+    /// Check whether the RX thread has ended, because the stream was closed or failed.
+    pub fn is_rx_finished(&self) -> bool {
+        self.rx_thread.is_finished()
+    }
+
+    /// Wait until the RX thread has ended. Returns the error which ended the thread, if any.
+    /// The interface can still be used to transmit frames afterwards.
+    pub fn join(self) -> io::Result<()> {
+        self.rx_thread
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("KISS RX thread panicked")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::atomic::AtomicBool, sync::atomic::Ordering, time::Duration, vec::Vec};
+
+    #[derive(Clone, Default)]
+    struct Sink(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Sink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn tx_lock_serializes_frames() {
+        let sink = Sink::default();
+        let driver = Arc::new(TxDriver::new(sink.clone()));
+        let driver_data = Arc::as_ptr(&driver) as *mut c_void;
+        let second_done = Arc::new(AtomicBool::new(false));
+
+        // SAFETY: The driver data points to a valid driver.
+        unsafe {
+            csp_usart_lock(driver_data);
+            assert_eq!(tx(driver_data, [1, 2].as_ptr(), 2), 0);
+        }
+        let second = {
+            let driver = driver.clone();
+            let second_done = second_done.clone();
+            thread::spawn(move || {
+                let driver_data = Arc::as_ptr(&driver) as *mut c_void;
+                // SAFETY: The driver data points to a valid driver.
+                unsafe {
+                    csp_usart_lock(driver_data);
+                    tx(driver_data, [3].as_ptr(), 1);
+                    csp_usart_unlock(driver_data);
+                }
+                second_done.store(true, Ordering::Relaxed);
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!second_done.load(Ordering::Relaxed));
+        // SAFETY: The driver data points to a valid driver.
+        unsafe {
+            tx(driver_data, [4].as_ptr(), 1);
+            csp_usart_unlock(driver_data);
+        }
+        second.join().unwrap();
+        assert_eq!(*sink.0.lock().unwrap(), [1, 2, 4, 3]);
+    }
+}
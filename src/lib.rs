@@ -7,17 +7,60 @@
 #![no_std]
 #[cfg(feature = "alloc")]
 extern crate alloc;
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
 #[cfg(any(feature = "std", test))]
 extern crate std;
 
-use core::time::Duration;
+use core::{
+    ffi::CStr,
+    fmt::{self, Debug, Display},
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
 
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
 
 use bitflags::bitflags;
 use ffi::{csp_conn_s, csp_packet_s, csp_socket_s};
 pub use libcsp_sys as ffi;
 
+#[cfg(feature = "std")]
+pub mod bridge;
+#[cfg(feature = "std")]
+pub mod callback;
+#[cfg(feature = "can")]
+pub mod can;
+pub mod cmp;
+pub mod conn;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+#[cfg(feature = "std")]
+pub mod driver;
+#[cfg(feature = "hmac")]
+pub mod hmac;
+#[cfg(feature = "hooks")]
+pub mod hooks;
+pub mod iflist;
+#[cfg(feature = "kiss")]
+pub mod kiss;
+pub mod promisc;
+pub mod rdp;
+#[cfg(feature = "alloc")]
+pub mod routing;
+pub mod services;
+pub mod sfp;
+#[cfg(feature = "hooks")]
+pub mod tap;
+#[cfg(feature = "std")]
+pub mod tun;
+#[cfg(feature = "std")]
+pub mod udp;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum ReservedPort {
     Cmp = 0,
@@ -29,10 +72,12 @@ pub enum ReservedPort {
     Uptime = 6,
 }
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+/// Error codes returned by `libcsp`.
+///
+/// Unknown error codes are converted to [ErrorCode::Unknown] instead of causing a panic.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, FromPrimitive, IntoPrimitive)]
 #[repr(i32)]
-pub enum CspError {
-    None = 0,
+pub enum ErrorCode {
     NoMem = -1,
     Inval = -2,
     TimedOut = -3,
@@ -49,13 +94,80 @@ pub enum CspError {
     Hmac = -100,
     Crc32 = -102,
     Sfp = -103,
+    #[num_enum(catch_all)]
+    Unknown(i32) = i32::MIN,
 }
 
-/// Listen on all ports, primarily used with [csp_bind]
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCode::NoMem => write!(f, "not enough memory"),
+            ErrorCode::Inval => write!(f, "invalid argument"),
+            ErrorCode::TimedOut => write!(f, "operation timed out"),
+            ErrorCode::Used => write!(f, "resource already in use"),
+            ErrorCode::NotSup => write!(f, "operation not supported"),
+            ErrorCode::Busy => write!(f, "device or resource busy"),
+            ErrorCode::Already => write!(f, "operation already in progress"),
+            ErrorCode::Reset => write!(f, "connection reset"),
+            ErrorCode::NoBufs => write!(f, "no more buffer space available"),
+            ErrorCode::Tx => write!(f, "transmission failed"),
+            ErrorCode::Driver => write!(f, "error in driver layer"),
+            ErrorCode::Again => write!(f, "resource temporarily unavailable"),
+            ErrorCode::NoSys => write!(f, "function not implemented"),
+            ErrorCode::Hmac => write!(f, "HMAC failed"),
+            ErrorCode::Crc32 => write!(f, "CRC32 failed"),
+            ErrorCode::Sfp => write!(f, "SFP protocol error or inconsistency"),
+            ErrorCode::Unknown(code) => write!(f, "unknown error code {}", code),
+        }
+    }
+}
+
+/// Error type returned by the wrappers of this crate.
+///
+/// It contains the [ErrorCode] and the name of the `libcsp` operation which failed.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct CspError {
+    op: &'static str,
+    code: ErrorCode,
+}
+
+impl CspError {
+    pub const fn new(op: &'static str, code: ErrorCode) -> Self {
+        Self { op, code }
+    }
+
+    /// Converts a `libcsp` result code to a [Result].
+    pub fn check(op: &'static str, result: i32) -> Result<(), Self> {
+        if result == 0 {
+            return Ok(());
+        }
+        Err(Self::new(op, ErrorCode::from(result)))
+    }
+
+    /// Name of the `libcsp` operation which failed.
+    pub fn op(&self) -> &'static str {
+        self.op
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+}
+
+impl Display for CspError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.op, self.code)
+    }
+}
+
+impl core::error::Error for CspError {}
+
+/// Listen on all ports, primarily used with [Socket::bind]
 pub const CSP_ANY: u8 = 255;
 pub const CSP_LOOPBACK: u16 = 0;
 
 bitflags! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
     pub struct SocketFlags: u32 {
         const NONE = 0x0000;
         /// RDP required.
@@ -70,7 +182,7 @@ bitflags! {
         const CRC32REQ = 0x0040;
         const CRC32PROHIB = 0x0080;
         const CONN_LESS = 0x0100;
-        /// Copy opts from incoming packets. Only applies to [csp_sendto_reply]
+        /// Copy opts from incoming packets. Only applies to [DatagramSocket::reply]
         const SAME = 0x8000;
 
         // The source may set any bits
@@ -79,6 +191,7 @@ bitflags! {
 }
 
 bitflags! {
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
     pub struct ConnectOpts: u32 {
         const NONE = SocketFlags::NONE.bits();
 
@@ -128,115 +241,249 @@ pub enum MsgPriority {
     Low = 3,
 }
 
-pub struct CspPacket(pub csp_packet_s);
+bitflags! {
+    /// Flags of the CSP header.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    pub struct HeaderFlags: u8 {
+        /// Use fragmentation.
+        const FRAG = 0x10;
+        /// Use HMAC verification.
+        const HMAC = 0x08;
+        /// Use the RDP protocol.
+        const RDP = 0x02;
+        /// Use a CRC32 checksum.
+        const CRC32 = 0x01;
 
-pub struct CspPacketRef(*mut csp_packet_s);
+        // The source may set any bits
+        const _ = !0;
+    }
+}
 
-pub struct CspPacketMut(*mut csp_packet_s);
+/// Typed representation of the CSP identifier/header [ffi::csp_id_t].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CspId {
+    pub prio: MsgPriority,
+    pub flags: HeaderFlags,
+    pub src: u16,
+    pub dst: u16,
+    pub dport: u8,
+    pub sport: u8,
+}
 
-impl From<CspPacketMut> for CspPacketRef {
-    fn from(value: CspPacketMut) -> Self {
-        Self(value.0)
+impl From<ffi::csp_id_t> for CspId {
+    fn from(id: ffi::csp_id_t) -> Self {
+        let prio = match id.pri & 0b11 {
+            0 => MsgPriority::Critical,
+            1 => MsgPriority::High,
+            2 => MsgPriority::Normal,
+            _ => MsgPriority::Low,
+        };
+        Self {
+            prio,
+            flags: HeaderFlags::from_bits_retain(id.flags),
+            src: id.src,
+            dst: id.dst,
+            dport: id.dport,
+            sport: id.sport,
+        }
     }
 }
 
-impl CspPacketRef {
-    pub fn packet_data(&self) -> &[u8] {
-        unsafe { &(*self.0).packet_data_union.data[..self.packet_length()] }
+impl From<CspId> for ffi::csp_id_t {
+    fn from(id: CspId) -> Self {
+        Self {
+            pri: id.prio.into(),
+            flags: id.flags.bits(),
+            src: id.src,
+            dst: id.dst,
+            dport: id.dport,
+            sport: id.sport,
+        }
     }
+}
 
-    pub fn whole_data(&self) -> &[u8; ffi::CSP_BUFFER_SIZE] {
-        unsafe { &(*self.0).packet_data_union.data }
+/// Owned CSP packet buffer.
+///
+/// Packets are allocated from the `libcsp` buffer pool with [CspStack::buffer_get] or are
+/// received from the stack. The buffer is returned to the pool with [ffi::csp_buffer_free] when
+/// the packet is dropped. APIs which pass the packet to `libcsp`, for example [csp_send], consume
+/// the packet.
+///
+/// The packet dereferences to its payload. The [core::fmt::Write] and [std::io::Write]
+/// implementations append to the payload and are bounded by the buffer capacity
+/// [ffi::CSP_BUFFER_SIZE].
+pub struct Packet(NonNull<csp_packet_s>);
+
+// SAFETY: The packet buffer is owned exclusively by this structure.
+unsafe impl Send for Packet {}
+
+impl Packet {
+    /// Capacity of the packet payload.
+    pub const CAPACITY: usize = ffi::CSP_BUFFER_SIZE;
+
+    /// Take ownership of a raw packet. Returns [None] if the pointer is NULL.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must point to a packet buffer allocated from the `libcsp` buffer pool which
+    /// is not used anywhere else.
+    pub unsafe fn from_raw(packet: *mut csp_packet_s) -> Option<Self> {
+        NonNull::new(packet).map(Self)
     }
 
-    pub fn packet_length(&self) -> usize {
-        unsafe { (*self.0).length.into() }
+    /// Release ownership of the packet buffer without freeing it.
+    pub fn into_raw(self) -> *mut csp_packet_s {
+        let packet = self.0.as_ptr();
+        core::mem::forget(self);
+        packet
     }
 
-    pub fn inner(&self) -> *const csp_packet_s {
-        self.0
+    pub fn as_ptr(&self) -> *const csp_packet_s {
+        self.0.as_ptr()
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut csp_packet_s {
+        self.0.as_ptr()
     }
-}
 
-pub struct CspPacketRefGuard(Option<CspPacketRef>);
+    fn inner(&self) -> &csp_packet_s {
+        // SAFETY: The pointer is valid and owned by this structure.
+        unsafe { self.0.as_ref() }
+    }
 
-impl Drop for CspPacketRefGuard {
-    fn drop(&mut self) {
-        if let Some(packet) = self.0.take() {
-            csp_buffer_free(packet)
-        }
+    fn inner_mut(&mut self) -> &mut csp_packet_s {
+        // SAFETY: The pointer is valid and owned by this structure.
+        unsafe { self.0.as_mut() }
     }
-}
 
-impl CspPacketRefGuard {
-    /// Take the packet out of the guard, preventing it from being freed.
-    pub fn take(mut self) -> CspPacketRef {
-        self.0.take().unwrap()
+    /// The CSP identifier/header of the packet.
+    pub fn id(&self) -> CspId {
+        self.inner().id.into()
     }
-}
 
-impl AsRef<CspPacketRef> for CspPacketRefGuard {
-    fn as_ref(&self) -> &CspPacketRef {
-        self.0.as_ref().unwrap()
+    pub fn set_id(&mut self, id: CspId) {
+        self.inner_mut().id = id.into();
     }
-}
 
-impl CspPacketMut {
-    pub fn packet_data(&self) -> &[u8] {
-        unsafe { &(*self.0).packet_data_union.data[..self.packet_length()] }
+    /// Length of the payload. The length of received packets is set by `libcsp` and the
+    /// interface drivers, so it is clamped to [Self::CAPACITY].
+    pub fn len(&self) -> usize {
+        usize::from(self.inner().length).min(Self::CAPACITY)
     }
 
-    pub fn whole_data(&self) -> &[u8; ffi::CSP_BUFFER_SIZE] {
-        unsafe { &(*self.0).packet_data_union.data }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    pub fn packet_length(&self) -> usize {
-        unsafe { (*self.0).length.into() }
+    /// Remaining capacity of the payload.
+    pub fn remaining(&self) -> usize {
+        Self::CAPACITY - self.len()
     }
 
-    pub fn inner(&self) -> *const csp_packet_s {
-        self.0
+    /// Set the payload length. Returns [ErrorCode::Inval] if the length exceeds the capacity.
+    pub fn set_len(&mut self, len: usize) -> Result<(), CspError> {
+        if len > Self::CAPACITY {
+            return Err(CspError::new("set_len", ErrorCode::Inval));
+        }
+        self.inner_mut().length = len as u16;
+        Ok(())
     }
 
-    pub fn whole_data_mut(&mut self) -> &mut [u8; ffi::CSP_BUFFER_SIZE] {
-        unsafe { &mut (*self.0).packet_data_union.data }
+    pub fn clear(&mut self) {
+        self.inner_mut().length = 0;
     }
 
-    pub fn set_data(&mut self, data: &[u8]) -> bool {
-        if data.len() > self.whole_data().len() {
-            return false;
+    /// Replace the payload with the given data. Returns [ErrorCode::Inval] if the data does
+    /// not fit into the packet.
+    pub fn set_data(&mut self, data: &[u8]) -> Result<(), CspError> {
+        if data.len() > Self::CAPACITY {
+            return Err(CspError::new("set_data", ErrorCode::Inval));
         }
-        self.whole_data_mut()[0..data.len()].copy_from_slice(data);
-        unsafe {
-            (*self.0).length = data.len() as u16;
+        self.buffer_mut()[..data.len()].copy_from_slice(data);
+        self.inner_mut().length = data.len() as u16;
+        Ok(())
+    }
+
+    /// Append data to the payload. Returns [ErrorCode::Inval] if the data does not fit into
+    /// the packet, in which case the packet is left unchanged.
+    pub fn extend_from_slice(&mut self, data: &[u8]) -> Result<(), CspError> {
+        let len = self.len();
+        if data.len() > self.remaining() {
+            return Err(CspError::new("extend_from_slice", ErrorCode::Inval));
         }
-        true
+        self.buffer_mut()[len..len + data.len()].copy_from_slice(data);
+        self.inner_mut().length = (len + data.len()) as u16;
+        Ok(())
     }
 
-    pub fn inner_mut(&self) -> *mut csp_packet_s {
-        self.0
+    /// The whole packet buffer, independently of the payload length.
+    pub fn buffer(&self) -> &[u8; ffi::CSP_BUFFER_SIZE] {
+        // SAFETY: All fields of the data union are plain integer arrays.
+        unsafe { &self.inner().packet_data_union.data }
+    }
+
+    /// Mutable access to the whole packet buffer, independently of the payload length.
+    pub fn buffer_mut(&mut self) -> &mut [u8; ffi::CSP_BUFFER_SIZE] {
+        // SAFETY: All fields of the data union are plain integer arrays.
+        unsafe { &mut self.inner_mut().packet_data_union.data }
     }
 }
 
-impl CspPacket {
-    pub fn new() -> Self {
-        Self::default()
+impl Deref for Packet {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer()[..self.len()]
     }
 }
 
-impl Default for CspPacket {
-    fn default() -> Self {
-        Self(csp_packet_s {
-            packet_info: Default::default(),
-            length: Default::default(),
-            id: Default::default(),
-            next: core::ptr::null_mut(),
-            header: Default::default(),
-            packet_data_union: Default::default(),
-        })
+impl DerefMut for Packet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let len = self.len();
+        &mut self.buffer_mut()[..len]
     }
 }
 
+impl Debug for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Packet")
+            .field("id", &self.id())
+            .field("data", &self.deref())
+            .finish()
+    }
+}
+
+impl fmt::Write for Packet {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.extend_from_slice(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Write for Packet {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = core::cmp::min(buf.len(), self.remaining());
+        // Can not fail, the length was checked.
+        let _ = self.extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for Packet {
+    fn drop(&mut self) {
+        // SAFETY: FFI call. We own the packet buffer.
+        unsafe { ffi::csp_buffer_free(self.0.as_ptr() as *const libc::c_void) }
+    }
+}
+
+/// Raw storage for a CSP socket.
+///
+/// `libcsp` keeps a pointer to the socket after it was bound, so this storage should not be used
+/// directly. Use the [Socket] type instead, which keeps the storage at a stable address.
 #[derive(Default)]
 pub struct CspSocket(pub csp_socket_s);
 
@@ -246,51 +493,605 @@ impl CspSocket {
     }
 }
 
-/// Rust wrapper for [ffi::csp_init]. Initialize the CSP stack.
+/// Owned CSP socket.
 ///
-/// # Safety
+/// The underlying [CspSocket] storage is either heap allocated or has a static lifetime, so it
+/// keeps its address when the socket is moved. This is required because `libcsp` keeps a raw
+/// pointer to the socket after it was bound with [Self::bind].
 ///
-/// - You must call this function only once.
-pub unsafe fn csp_init() {
-    // SAFETY: FFI call
-    unsafe {
-        ffi::csp_init();
+/// The socket is unbound from its port with [ffi::csp_socket_close] when it is dropped.
+/// Connections which were not accepted yet are closed as well.
+pub struct Socket {
+    // Dropped after the socket was closed in the drop handler.
+    storage: SocketStorage,
+    port: Option<u8>,
+}
+
+/// Storage of a [Socket] with a stable address, which is freed on drop if it was allocated.
+struct SocketStorage {
+    ptr: NonNull<CspSocket>,
+    /// Set if the storage was allocated by [Self::new_boxed] and must be freed on drop.
+    #[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+    boxed: bool,
+}
+
+impl SocketStorage {
+    #[cfg(feature = "alloc")]
+    fn new_boxed(opts: SocketFlags) -> Self {
+        let mut storage = Box::<CspSocket>::default();
+        storage.0.opts = opts.bits();
+        Self {
+            ptr: NonNull::from(Box::leak(storage)),
+            boxed: true,
+        }
+    }
+
+    fn new_static(storage: &'static mut CspSocket, opts: SocketFlags) -> Self {
+        *storage = CspSocket::default();
+        storage.0.opts = opts.bits();
+        Self {
+            ptr: NonNull::from(storage),
+            boxed: false,
+        }
+    }
+
+    fn as_ptr(&self) -> *mut csp_socket_s {
+        // SAFETY: The storage pointer is valid for the whole lifetime of the storage.
+        unsafe { core::ptr::addr_of_mut!((*self.ptr.as_ptr()).0) }
     }
 }
 
-/// Rust wrapper for [ffi::csp_bind].
-pub fn csp_bind(socket: &mut CspSocket, port: u8) {
-    // SAFETY: FFI call
-    unsafe {
-        ffi::csp_bind(socket.inner_as_mut_ptr(), port);
+impl Drop for SocketStorage {
+    fn drop(&mut self) {
+        #[cfg(feature = "alloc")]
+        if self.boxed {
+            // SAFETY: The storage was leaked from a box in [Self::new_boxed]. The socket was
+            // closed before, so libcsp does not reference it anymore.
+            drop(unsafe { Box::from_raw(self.ptr.as_ptr()) });
+        }
     }
 }
 
-/// Rust wrapper for [ffi::csp_listen].
-pub fn csp_listen(socket: &mut CspSocket, backlog: usize) {
-    // SAFETY: FFI call
-    unsafe {
-        ffi::csp_listen(socket.inner_as_mut_ptr(), backlog);
+// SAFETY: The socket storage is only accessed through the thread-safe libcsp API.
+unsafe impl Send for Socket {}
+// SAFETY: The socket storage is only accessed through the thread-safe libcsp API.
+unsafe impl Sync for Socket {}
+
+impl Socket {
+    /// Create a new unbound socket with heap allocated storage.
+    #[cfg(feature = "alloc")]
+    pub fn new(opts: SocketFlags) -> Self {
+        Self {
+            storage: SocketStorage::new_boxed(opts),
+            port: None,
+        }
+    }
+
+    /// Create a new unbound socket which uses the given static storage. This can be used on
+    /// systems without an allocator.
+    pub fn new_static(storage: &'static mut CspSocket, opts: SocketFlags) -> Self {
+        Self {
+            storage: SocketStorage::new_static(storage, opts),
+            port: None,
+        }
+    }
+
+    fn inner_ptr(&self) -> *mut csp_socket_s {
+        self.storage.as_ptr()
+    }
+
+    /// Socket options.
+    pub fn opts(&self) -> SocketFlags {
+        // SAFETY: The options are only written before the socket is bound.
+        SocketFlags::from_bits_retain(unsafe { (*self.inner_ptr()).opts })
+    }
+
+    /// The port the socket is bound to, or [None] if it was not bound yet.
+    pub fn port(&self) -> Option<u8> {
+        self.port
+    }
+
+    /// Rust wrapper for [ffi::csp_bind]. Use [CSP_ANY] to bind the socket to all ports which
+    /// are not bound to another socket.
+    ///
+    /// Returns [ErrorCode::Used] if the port is already bound to another socket and
+    /// [ErrorCode::Already] if this socket was already bound.
+    pub fn bind(&mut self, _stack: &CspStack, port: u8) -> Result<(), CspError> {
+        if self.port.is_some() {
+            return Err(CspError::new("csp_bind", ErrorCode::Already));
+        }
+        // SAFETY: FFI call. The socket storage has a stable address and outlives the binding,
+        // which is removed on drop.
+        let result = unsafe { ffi::csp_bind(self.inner_ptr(), port) };
+        CspError::check("csp_bind", result)?;
+        self.port = Some(port);
+        Ok(())
+    }
+
+    /// Rust wrapper for [ffi::csp_listen].
+    pub fn listen(&mut self, backlog: usize) -> Result<(), CspError> {
+        // SAFETY: FFI call.
+        let result = unsafe { ffi::csp_listen(self.inner_ptr(), backlog) };
+        CspError::check("csp_listen", result)
+    }
+
+    /// Rust wrapper for [ffi::csp_accept]. The accepted connection is closed when the returned
+    /// guard is dropped.
+    ///
+    /// Returns [ErrorCode::TimedOut] if no connection arrived in time.
+    pub fn accept(&self, timeout: Duration) -> Result<CspConnGuard, CspError> {
+        // SAFETY: FFI call.
+        let conn = unsafe { ffi::csp_accept(self.inner_ptr(), timeout_millis(timeout)) };
+        if conn.is_null() {
+            return Err(CspError::new("csp_accept", ErrorCode::TimedOut));
+        }
+        Ok(CspConnGuard(CspConnRef(conn)))
+    }
+
+    /// Iterator over incoming connections. Each call to [Iterator::next] waits up to `timeout`
+    /// for a new connection, and the iteration ends if no connection arrives in that time.
+    pub fn incoming(&self, timeout: Duration) -> Incoming<'_> {
+        Incoming {
+            socket: self,
+            timeout,
+        }
+    }
+
+    /// Close the socket explicitly. This is also done when the socket is dropped.
+    pub fn close(mut self) -> Result<(), CspError> {
+        self.close_generic()
+    }
+
+    fn close_generic(&mut self) -> Result<(), CspError> {
+        if self.port.take().is_none() {
+            return Ok(());
+        }
+        // SAFETY: FFI call.
+        let result = unsafe { ffi::csp_socket_close(self.inner_ptr()) };
+        // Clean up everything which was queued before the socket was unbound.
+        if self.opts().contains(SocketFlags::CONN_LESS) {
+            while let Ok(packet) = csp_recvfrom(self, Duration::ZERO) {
+                drop(packet);
+            }
+        } else {
+            while let Ok(conn) = self.accept(Duration::ZERO) {
+                drop(conn);
+            }
+        }
+        CspError::check("csp_socket_close", result)
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let _ = self.close_generic();
+    }
+}
+
+/// Address and port of a CSP endpoint.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CspEndpoint {
+    pub addr: u16,
+    pub port: u8,
+}
+
+impl CspEndpoint {
+    /// Source of a packet with the given header.
+    pub fn source(id: &CspId) -> Self {
+        Self {
+            addr: id.src,
+            port: id.sport,
+        }
+    }
+}
+
+/// Options of a reply sent with [DatagramSocket::reply] by a socket with the given options.
+fn reply_opts(socket_opts: SocketFlags) -> ConnectOpts {
+    if socket_opts.contains(SocketFlags::SAME) {
+        ConnectOpts::SAME
+    } else {
+        ConnectOpts::NONE
+    }
+}
+
+/// Connection-less CSP socket for datagram-style services.
+///
+/// This is a [Socket] which always has the [SocketFlags::CONN_LESS] option set. Packets are
+/// received with [Self::recv_from] and sent without a connection with [Self::send_to] or as a
+/// reply to a received request with [Self::reply].
+pub struct DatagramSocket(Socket);
+
+impl DatagramSocket {
+    /// Create a new unbound connection-less socket with heap allocated storage.
+    #[cfg(feature = "alloc")]
+    pub fn new(opts: SocketFlags) -> Self {
+        Self(Socket::new(opts | SocketFlags::CONN_LESS))
+    }
+
+    /// Create a new unbound connection-less socket which uses the given static storage.
+    pub fn new_static(storage: &'static mut CspSocket, opts: SocketFlags) -> Self {
+        Self(Socket::new_static(storage, opts | SocketFlags::CONN_LESS))
+    }
+
+    /// Socket options.
+    pub fn opts(&self) -> SocketFlags {
+        self.0.opts()
+    }
+
+    /// The port the socket is bound to, or [None] if it was not bound yet.
+    pub fn port(&self) -> Option<u8> {
+        self.0.port()
+    }
+
+    /// See [Socket::bind].
+    pub fn bind(&mut self, stack: &CspStack, port: u8) -> Result<(), CspError> {
+        self.0.bind(stack, port)
+    }
+
+    /// Rust wrapper for [ffi::csp_recvfrom] which also returns the source of the packet.
+    ///
+    /// Returns [ErrorCode::TimedOut] if no packet arrived in time.
+    pub fn recv_from(&self, timeout: Duration) -> Result<(Packet, CspEndpoint), CspError> {
+        let packet = csp_recvfrom(&self.0, timeout)?;
+        let src = CspEndpoint::source(&packet.id());
+        Ok((packet, src))
+    }
+
+    /// Rust wrapper for [ffi::csp_sendto]. The packet is sent without opening a connection.
+    pub fn send_to(
+        &self,
+        prio: MsgPriority,
+        dst: u16,
+        dport: u8,
+        sport: u8,
+        opts: ConnectOpts,
+        packet: Packet,
+    ) {
+        // SAFETY: FFI call. The ownership of the packet is passed to libcsp.
+        unsafe {
+            ffi::csp_sendto(
+                prio.into(),
+                dst,
+                dport,
+                sport,
+                opts.bits(),
+                packet.into_raw(),
+            )
+        }
+    }
+
+    /// Send a packet as a reply to a request received with [Self::recv_from].
+    ///
+    /// If the socket was created with [SocketFlags::SAME], the reply uses the same options
+    /// as the request. Otherwise, no options are set.
+    pub fn reply(&self, request: &Packet, packet: Packet) {
+        self.reply_w_opts(request, packet, reply_opts(self.opts()))
+    }
+
+    /// Rust wrapper for [ffi::csp_sendto_reply]. Use [ConnectOpts::SAME] to copy the options
+    /// from the request.
+    pub fn reply_w_opts(&self, request: &Packet, packet: Packet, opts: ConnectOpts) {
+        // SAFETY: FFI call. The ownership of the reply packet is passed to libcsp.
+        unsafe { ffi::csp_sendto_reply(request.as_ptr(), packet.into_raw(), opts.bits()) }
+    }
+
+    /// Close the socket explicitly. This is also done when the socket is dropped.
+    pub fn close(self) -> Result<(), CspError> {
+        self.0.close()
+    }
+}
+
+/// Iterator over the incoming connections of a [Socket], created by [Socket::incoming].
+pub struct Incoming<'sock> {
+    socket: &'sock Socket,
+    timeout: Duration,
+}
+
+impl Iterator for Incoming<'_> {
+    type Item = CspConnGuard;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.socket.accept(self.timeout).ok()
     }
 }
 
-/// Rust wrapper for [ffi::csp_route_work].
-pub fn csp_route_work_raw() -> i32 {
-    unsafe { ffi::csp_route_work() }
+/// Name of a `libcsp` interface. Returns an empty string if the interface has no valid name.
+///
+/// # Safety
+///
+/// The interface pointer must be NULL or point to a valid interface.
+#[cfg_attr(not(feature = "alloc"), allow(dead_code))]
+pub(crate) unsafe fn iface_name<'a>(iface: *const ffi::csp_iface_t) -> &'a str {
+    // SAFETY: See function safety documentation.
+    match unsafe { iface.as_ref() } {
+        Some(iface) if !iface.name.is_null() => {
+            // SAFETY: Interface names are NUL terminated strings which live as long as the
+            // interface.
+            unsafe { CStr::from_ptr(iface.name) }.to_str().unwrap_or("")
+        }
+        _ => "",
+    }
 }
 
-/// Rust wrapper for [ffi::csp_route_work] which also converts errors to the [CspError] type.
-/// This function will panic if the returned error type is not among the known values of
-/// [CspError].
+/// Converts a timeout to milliseconds for the libcsp API. Timeouts which are too large are
+/// clamped to the maximum timeout, which means waiting forever.
+fn timeout_millis(timeout: Duration) -> u32 {
+    timeout.as_millis().try_into().unwrap_or(u32::MAX)
+}
+
+/// Protocol version used by the CSP stack.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum ProtocolVersion {
+    V1 = 1,
+    V2 = 2,
+}
+
+/// Packet deduplication mode of the CSP stack.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, TryFromPrimitive, IntoPrimitive)]
+#[repr(u8)]
+pub enum DedupMode {
+    Off = ffi::csp_dedup_types_CSP_DEDUP_OFF as u8,
+    /// Deduplication on forwarded packets only.
+    Forward = ffi::csp_dedup_types_CSP_DEDUP_FWD as u8,
+    /// Deduplication on incoming packets only.
+    Incoming = ffi::csp_dedup_types_CSP_DEDUP_INCOMING as u8,
+    /// Deduplication on incoming and forwarded packets.
+    All = ffi::csp_dedup_types_CSP_DEDUP_ALL as u8,
+}
+
+/// Configuration of the CSP stack. It is written to [ffi::csp_conf] by [CspStack::init].
 ///
-/// [csp_route_work_raw] can be used if this is not acceptable.
-pub fn csp_route_work() -> Result<(), CspError> {
-    let result = unsafe { ffi::csp_route_work() };
-    if result == CspError::None as i32 {
-        return Ok(());
+/// The default values are the same ones `libcsp` uses.
+#[derive(Debug, Copy, Clone)]
+pub struct CspConfig {
+    /// Host name, returned by the CMP ident request.
+    pub hostname: &'static CStr,
+    /// Model, returned by the CMP ident request.
+    pub model: &'static CStr,
+    /// Revision, returned by the CMP ident request.
+    pub revision: &'static CStr,
+    pub version: ProtocolVersion,
+    /// Default connection options. These will always be or'ed onto new connections.
+    pub conn_dfl_so: ConnectOpts,
+    pub dedup: DedupMode,
+}
+
+impl Default for CspConfig {
+    fn default() -> Self {
+        Self {
+            hostname: c"",
+            model: c"",
+            revision: c"",
+            version: ProtocolVersion::V2,
+            conn_dfl_so: ConnectOpts::NONE,
+            dedup: DedupMode::Off,
+        }
+    }
+}
+
+const STACK_UNINIT: u8 = 0;
+const STACK_INITIALIZING: u8 = 1;
+const STACK_READY: u8 = 2;
+
+static STACK_STATE: AtomicU8 = AtomicU8::new(STACK_UNINIT);
+
+/// Handle to the initialized CSP stack.
+///
+/// This handle can only be created by calling [Self::init], which configures and initializes the
+/// `libcsp` library exactly once. All operations which require an initialized stack are exposed
+/// as methods of this handle, so forgetting to initialize the stack is a compile-time error.
+/// The handle is a cheap zero-sized token which can be copied and sent to other threads.
+#[derive(Debug, Copy, Clone)]
+pub struct CspStack {
+    _priv: (),
+}
+
+impl CspStack {
+    /// Configure the CSP stack with the given configuration and initialize it with
+    /// [ffi::csp_init].
+    ///
+    /// Returns [ErrorCode::Already] if the stack was already initialized. [Self::get] can be used
+    /// to retrieve the handle of an initialized stack.
+    pub fn init(cfg: &CspConfig) -> Result<Self, CspError> {
+        if STACK_STATE
+            .compare_exchange(
+                STACK_UNINIT,
+                STACK_INITIALIZING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .is_err()
+        {
+            return Err(CspError::new("csp_init", ErrorCode::Already));
+        }
+        // SAFETY: The state guard above ensures that only one thread ever gets here, so there
+        // are no concurrent accesses to the configuration. The configured strings are static.
+        unsafe {
+            let conf = core::ptr::addr_of_mut!(ffi::csp_conf);
+            (*conf).version = cfg.version.into();
+            (*conf).hostname = cfg.hostname.as_ptr();
+            (*conf).model = cfg.model.as_ptr();
+            (*conf).revision = cfg.revision.as_ptr();
+            (*conf).conn_dfl_so = cfg.conn_dfl_so.bits();
+            (*conf).dedup = cfg.dedup.into();
+            ffi::csp_init();
+        }
+        STACK_STATE.store(STACK_READY, Ordering::Release);
+        Ok(Self { _priv: () })
+    }
+
+    /// Retrieve the stack handle if the stack was already initialized with [Self::init].
+    pub fn get() -> Option<Self> {
+        if STACK_STATE.load(Ordering::Acquire) == STACK_READY {
+            return Some(Self { _priv: () });
+        }
+        None
+    }
+
+    /// Rust wrapper for [ffi::csp_route_work].
+    ///
+    /// Returns [ErrorCode::TimedOut] if there was no packet to route.
+    pub fn route_work(&self) -> Result<(), CspError> {
+        // SAFETY: FFI call.
+        CspError::check("csp_route_work", unsafe { ffi::csp_route_work() })
+    }
+
+    /// Rust wrapper for [ffi::csp_connect].
+    ///
+    /// `libcsp` does not report why establishing the connection failed. [ErrorCode::NoMem] is
+    /// returned if there was no free connection or the RDP handshake failed.
+    pub fn connect(
+        &self,
+        prio: MsgPriority,
+        dst: u16,
+        dst_port: u8,
+        timeout: Duration,
+        opts: ConnectOpts,
+    ) -> Result<CspConnRef, CspError> {
+        // SAFETY: FFI call.
+        let conn = unsafe {
+            ffi::csp_connect(
+                prio as u8,
+                dst,
+                dst_port,
+                timeout_millis(timeout),
+                opts.bits(),
+            )
+        };
+        if conn.is_null() {
+            return Err(CspError::new("csp_connect", ErrorCode::NoMem));
+        }
+        Ok(CspConnRef(conn))
+    }
+
+    /// Rust wrapper for [ffi::csp_connect] which returns a guard structure. The connection will be
+    /// be closed automatically when the guard structure is dropped.
+    pub fn connect_guarded(
+        &self,
+        prio: MsgPriority,
+        dst: u16,
+        dst_port: u8,
+        timeout: Duration,
+        opts: ConnectOpts,
+    ) -> Result<CspConnGuard, CspError> {
+        Ok(CspConnGuard(
+            self.connect(prio, dst, dst_port, timeout, opts)?,
+        ))
+    }
+
+    /// Rust wrapper for [ffi::csp_buffer_get].
+    ///
+    /// Returns [ErrorCode::NoBufs] if no buffer is available.
+    pub fn buffer_get(&self) -> Result<Packet, CspError> {
+        // SAFETY: FFI call. The packet is a fresh buffer from the buffer pool.
+        unsafe {
+            // The size argument is unused
+            Packet::from_raw(ffi::csp_buffer_get(0))
+        }
+        .ok_or(CspError::new("csp_buffer_get", ErrorCode::NoBufs))
+    }
+
+    /// Rust wrapper for [ffi::csp_ping]. Returns the echo time on success.
+    ///
+    /// `libcsp` does not report why the ping failed, so [ErrorCode::TimedOut] is returned for
+    /// all failures.
+    pub fn ping(
+        &self,
+        node: u16,
+        timeout: Duration,
+        size: usize,
+        opts: SocketFlags,
+    ) -> Result<Duration, CspError> {
+        // SAFETY: FFI call.
+        let result = unsafe {
+            ffi::csp_ping(
+                node,
+                timeout_millis(timeout),
+                size as u32,
+                opts.bits() as u8,
+            )
+        };
+        if result < 0 {
+            return Err(CspError::new("csp_ping", ErrorCode::TimedOut));
+        }
+        Ok(Duration::from_millis(result as u64))
+    }
+
+    /// Rust wrapper for [ffi::csp_reboot].
+    pub fn reboot(&self, node: u16) {
+        // SAFETY: FFI call.
+        unsafe { ffi::csp_reboot(node) }
+    }
+
+    /// Rust wrapper for [ffi::csp_transaction_w_opts].
+    ///
+    /// # Parameters
+    ///
+    /// * `in_len`: Use [None] if the length is unknown, and the expected reply length otherwise.
+    ///   `in_data` must be at least [ffi::CSP_BUFFER_SIZE] bytes large if the length is unknown.
+    ///
+    /// # Returns
+    ///
+    /// The reply size on success, which is 0 if no reply was expected. [ErrorCode::Inval] is
+    /// returned if `out_data` does not fit into a packet or `in_data` is too small for the reply,
+    /// and [ErrorCode::TimedOut] if the transaction failed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transaction_w_opts(
+        &self,
+        prio: MsgPriority,
+        dst: u16,
+        dst_port: u8,
+        timeout: Duration,
+        out_data: &[u8],
+        in_data: &mut [u8],
+        in_len: Option<usize>,
+        opts: ConnectOpts,
+    ) -> Result<usize, CspError> {
+        const OP: &str = "csp_transaction_w_opts";
+        let out_len = transaction_out_len(OP, out_data)?;
+        let in_len_raw = transaction_in_len(OP, in_data, in_len)?;
+        // SAFETY: FFI call. We checked that the request fits into a packet and that the reply
+        // fits into the input buffer.
+        let result = unsafe {
+            ffi::csp_transaction_w_opts(
+                prio as u8,
+                dst,
+                dst_port,
+                timeout_millis(timeout),
+                out_data.as_ptr() as *const core::ffi::c_void,
+                out_len,
+                in_data.as_mut_ptr() as *mut core::ffi::c_void,
+                in_len_raw,
+                opts.bits(),
+            )
+        };
+        transaction_result(OP, result, in_len_raw)
+    }
+
+    /// Calls [Self::transaction_w_opts] with [ConnectOpts::NONE].
+    #[allow(clippy::too_many_arguments)]
+    pub fn transaction(
+        &self,
+        prio: MsgPriority,
+        dst: u16,
+        dst_port: u8,
+        timeout: Duration,
+        out_data: &[u8],
+        in_data: &mut [u8],
+        in_len: Option<usize>,
+    ) -> Result<usize, CspError> {
+        self.transaction_w_opts(
+            prio,
+            dst,
+            dst_port,
+            timeout,
+            out_data,
+            in_data,
+            in_len,
+            ConnectOpts::NONE,
+        )
     }
-    Err(CspError::try_from(result)
-        .unwrap_or_else(|_| panic!("unexpected error value {} from csp_route_work", result)))
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -312,7 +1113,7 @@ pub struct CspConnGuard(pub CspConnRef);
 
 impl Drop for CspConnGuard {
     fn drop(&mut self) {
-        csp_close(self.0);
+        let _ = csp_close(self.0);
     }
 }
 
@@ -356,96 +1157,27 @@ impl CspInterface {
     }
 }
 
-#[derive(Default)]
-pub struct CspUdpConf(pub ffi::csp_if_udp_conf_t);
-
-impl CspUdpConf {
-    pub fn new(addr: &'static str, lport: u16, rport: u16) -> Self {
-        Self(ffi::csp_if_udp_conf_t {
-            host: addr.as_ptr() as *mut i8,
-            lport: lport.into(),
-            rport: rport.into(),
-            server_handle: Default::default(),
-            peer_addr: libc::sockaddr_in {
-                sin_family: Default::default(),
-                sin_port: Default::default(),
-                sin_addr: libc::in_addr {
-                    s_addr: Default::default(),
-                },
-                sin_zero: Default::default(),
-            },
-            sockfd: Default::default(),
-        })
-    }
-}
-
-pub fn csp_accept_guarded(socket: &mut CspSocket, timeout: Duration) -> Option<CspConnGuard> {
-    Some(CspConnGuard(csp_accept(socket, timeout)?))
-}
-
-/// Rust wrapper for [ffi::csp_accept].
-pub fn csp_accept(socket: &mut CspSocket, timeout: Duration) -> Option<CspConnRef> {
-    let timeout_millis = timeout.as_millis();
-    if timeout_millis > u32::MAX as u128 {
-        return None;
-    }
-    Some(CspConnRef(unsafe {
-        let addr = ffi::csp_accept(socket.inner_as_mut_ptr(), timeout_millis as u32);
-        if addr.is_null() {
-            return None;
-        }
-        addr
-    }))
-}
-
-/// Rust wrapper for [ffi::csp_socket_close] which returns the result code directly.
-pub fn csp_socket_close_raw(sock: &mut CspSocket) -> i32 {
-    unsafe { ffi::csp_socket_close(&mut sock.0) }
-}
-
-/// Rust wrapper for [ffi::csp_socket_close].
-///
-/// This function will panic if the error code returned from [ffi::csp_socket_close] is not one of
-/// [CspError]. [csp_socket_close_raw] can be used if this is not acceptable.
-pub fn csp_socket_close(sock: &mut CspSocket) -> Result<(), CspError> {
-    let result = unsafe { ffi::csp_socket_close(&mut sock.0) };
-    Err(CspError::try_from(result)
-        .unwrap_or_else(|_| panic!("unexpected error value {} from csp_socket_close", result)))
-}
-
 /// Rust wrapper for [ffi::csp_read].
-pub fn csp_read(conn: &mut CspConnRef, timeout: Duration) -> Option<CspPacketRef> {
-    let timeout_millis = timeout.as_millis();
-    if timeout_millis > u32::MAX as u128 {
-        return None;
-    }
-    let opt_packet = unsafe { ffi::csp_read(conn.0, timeout_millis as u32) };
-    if opt_packet.is_null() {
-        return None;
-    }
-    // SAFETY: FFI pointer.
-    Some(CspPacketRef(unsafe { &mut *opt_packet }))
-}
-
-/// Rust wrapper for [ffi::csp_read] which returns a guarded packet reference. This packet
-/// will cleaned up automatically with [csp_buffer_free] on drop.
-pub fn csp_read_guarded(conn: &mut CspConnRef, timeout: Duration) -> Option<CspPacketRefGuard> {
-    Some(CspPacketRefGuard(Some(csp_read(conn, timeout)?)))
+///
+/// Returns [ErrorCode::TimedOut] if no packet arrived in time.
+pub fn csp_read(conn: &mut CspConnRef, timeout: Duration) -> Result<Packet, CspError> {
+    // SAFETY: FFI call. The ownership of the received packet is passed to us.
+    unsafe { Packet::from_raw(ffi::csp_read(conn.0, timeout_millis(timeout))) }
+        .ok_or(CspError::new("csp_read", ErrorCode::TimedOut))
 }
 
 /// Rust wrapper for [ffi::csp_recvfrom].
-pub fn csp_recvfrom(socket: &mut CspSocket, timeout: u32) -> Option<CspPacketRef> {
-    let opt_packet = unsafe { ffi::csp_recvfrom(&mut socket.0, timeout) };
-    if opt_packet.is_null() {
-        return None;
+///
+/// Returns [ErrorCode::TimedOut] if no packet arrived in time.
+pub fn csp_recvfrom(socket: &Socket, timeout: Duration) -> Result<Packet, CspError> {
+    // SAFETY: FFI call. The ownership of the received packet is passed to us.
+    unsafe {
+        Packet::from_raw(ffi::csp_recvfrom(
+            socket.inner_ptr(),
+            timeout_millis(timeout),
+        ))
     }
-    Some(CspPacketRef(unsafe { &mut *opt_packet }))
-}
-
-/// Rust wrapper for [ffi::csp_recvfrom] which returns a guarded packet reference. This packet
-/// will cleaned up automatically with [csp_buffer_free] on drop.
-pub fn csp_recvfrom_guarded(socket: &mut CspSocket, timeout: u32) -> Option<CspPacketRefGuard> {
-    Some(CspPacketRefGuard(Some(csp_recvfrom(socket, timeout)?)))
+    .ok_or(CspError::new("csp_recvfrom", ErrorCode::TimedOut))
 }
 
 /// Rust wrapper for [ffi::csp_conn_dport].
@@ -480,238 +1212,251 @@ pub fn csp_conn_flags(conn: &CspConnRef) -> i32 {
 
 /// Rust wrapper for [ffi::csp_conn_src] which also tries to convert the options to
 /// a [ConnectOpts] bitfield.
-pub fn csp_conn_flags_typed(conn: &CspConnRef) -> Option<ConnectOpts> {
+///
+/// Returns [ErrorCode::Inval] if the flags are negative or contain unknown options.
+pub fn csp_conn_flags_typed(conn: &CspConnRef) -> Result<ConnectOpts, CspError> {
     let flags_raw = csp_conn_flags(conn);
-    if flags_raw < 0 {
-        return None;
-    }
-    // SAFETY: FFI call.
-    ConnectOpts::from_bits(flags_raw as u32)
+    u32::try_from(flags_raw)
+        .ok()
+        .and_then(ConnectOpts::from_bits)
+        .ok_or(CspError::new("csp_conn_flags", ErrorCode::Inval))
 }
 
-pub fn csp_service_handler(packet: CspPacketRef) {
-    // SAFETY: FFI call.
-    unsafe { ffi::csp_service_handler(&mut *packet.0) }
+/// Rust wrapper for [ffi::csp_service_handler]. If a [services::ServiceHandler] was installed
+/// with [services::install], the packet is dispatched to it instead.
+pub fn csp_service_handler(packet: Packet) {
+    #[cfg(feature = "std")]
+    let Some(packet) = services::dispatch_installed(packet) else {
+        return;
+    };
+    // SAFETY: FFI call. The service handler takes ownership of the packet.
+    unsafe { ffi::csp_service_handler(packet.into_raw()) }
 }
 
 /// Rust wrapper for [ffi::csp_close].
-pub fn csp_close(conn: CspConnRef) -> i32 {
-    // SAFETY: FFI call.
-    unsafe { ffi::csp_close(conn.0) }
-}
-
-/// Rust wrapper for [ffi::csp_ping], returns the result code directly.
-pub fn csp_ping_raw(node: u16, timeout: Duration, size: usize, opts: SocketFlags) -> i32 {
+pub fn csp_close(conn: CspConnRef) -> Result<(), CspError> {
     // SAFETY: FFI call.
-    unsafe {
-        ffi::csp_ping(
-            node,
-            timeout.as_millis() as u32,
-            size as u32,
-            opts.bits() as u8,
-        )
-    }
-}
-
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub struct PingError;
-
-/// Rust wrapper for [ffi::csp_ping].
-pub fn csp_ping(
-    node: u16,
-    timeout: Duration,
-    size: usize,
-    opts: SocketFlags,
-) -> Result<Duration, PingError> {
-    let result = csp_ping_raw(node, timeout, size, opts);
-    if result < 0 {
-        return Err(PingError);
-    }
-    Ok(Duration::from_millis(result as u64))
-}
-
-/// Rust wrapper for [ffi::csp_reboot].
-pub fn csp_reboot(node: u16) {
-    // SAFETY: FFI call.
-    unsafe { ffi::csp_reboot(node) }
-}
-
-/// Rust wrapper for [ffi::csp_connect].
-pub fn csp_connect(
-    prio: MsgPriority,
-    dst: u16,
-    dst_port: u8,
-    timeout: Duration,
-    opts: ConnectOpts,
-) -> Option<CspConnRef> {
-    // SAFETY: FFI call.
-    let conn = unsafe {
-        ffi::csp_connect(
-            prio as u8,
-            dst,
-            dst_port,
-            timeout.as_millis() as u32,
-            opts.bits(),
-        )
-    };
-    if conn.is_null() {
-        return None;
-    }
-    // SAFETY: We checked that the pointer is valid.
-    Some(CspConnRef(conn))
-}
-
-/// Rust wrapper for [ffi::csp_connect] which returns a guard structure. The connection will be
-/// be closed automatically when the guard structure is dropped.
-pub fn csp_connect_guarded(
-    prio: MsgPriority,
-    dst: u16,
-    dst_port: u8,
-    timeout: Duration,
-    opts: ConnectOpts,
-) -> Option<CspConnGuard> {
-    Some(CspConnGuard(csp_connect(
-        prio, dst, dst_port, timeout, opts,
-    )?))
-}
-
-/// Rust wrapper for [ffi::csp_buffer_get].
-pub fn csp_buffer_get() -> Option<CspPacketMut> {
-    let packet_ref = unsafe {
-        // The size argument is unused
-        ffi::csp_buffer_get(0)
-    };
-    if packet_ref.is_null() {
-        return None;
-    }
-    // SAFETY: We checked that the pointer is valid.
-    Some(CspPacketMut(unsafe { &mut *packet_ref }))
+    CspError::check("csp_close", unsafe { ffi::csp_close(conn.0) })
 }
 
 /// Rust wrapper for [ffi::csp_send].
-pub fn csp_send(conn: &mut CspConnRef, packet: impl Into<CspPacketRef>) {
-    // SAFETY: FFI call.
-    unsafe { ffi::csp_send(conn.0, packet.into().0) }
+pub fn csp_send(conn: &mut CspConnRef, packet: Packet) {
+    // SAFETY: FFI call. The ownership of the packet is passed to libcsp.
+    unsafe { ffi::csp_send(conn.0, packet.into_raw()) }
 }
 
-/// Rust wrapper for [ffi::csp_conn_print_table].
+/// Rust wrapper for [ffi::csp_conn_print_table]. [conn::iter] and [conn::table_string] give
+/// access to the connection table without printing it.
 pub fn csp_conn_print_table() {
     // SAFETY: FFI call.
     unsafe { ffi::csp_conn_print_table() }
 }
 
-/// Rust wrapper for [ffi::csp_buffer_free].
-pub fn csp_buffer_free(packet: impl Into<CspPacketRef>) {
-    // SAFETY: FFI call and the Rust type system actually ensure the correct type
-    // is free'd here, while also taking the packet by value.
-    unsafe { ffi::csp_buffer_free(packet.into().0 as *const libc::c_void) }
-}
-
 /// Rust wrapper for [ffi::csp_transaction_persistent].
 ///
 /// # Parameters
 ///
 /// * `in_len`: Use [None] if the length is unknown, and the expected reply length otherwise.
+///   `in_data` must be at least [ffi::CSP_BUFFER_SIZE] bytes large if the length is unknown.
 ///
 /// # Returns
 ///
-/// 1 or reply size on success, 0 otherwise.
+/// The reply size on success, which is 0 if no reply was expected. [ErrorCode::Inval] is
+/// returned if `out_data` does not fit into a packet or `in_data` is too small for the reply,
+/// and [ErrorCode::TimedOut] if the transaction failed.
 pub fn csp_transaction_persistent(
     conn: &mut CspConnRef,
     timeout: Duration,
     out_data: &[u8],
     in_data: &mut [u8],
     in_len: Option<usize>,
-) -> i32 {
-    unsafe {
+) -> Result<usize, CspError> {
+    const OP: &str = "csp_transaction_persistent";
+    let out_len = transaction_out_len(OP, out_data)?;
+    let in_len_raw = transaction_in_len(OP, in_data, in_len)?;
+    // SAFETY: FFI call. We checked that the request fits into a packet and that the reply fits
+    // into the input buffer.
+    let result = unsafe {
         ffi::csp_transaction_persistent(
             conn.0,
-            timeout.as_millis() as u32,
+            timeout_millis(timeout),
             out_data.as_ptr() as *const core::ffi::c_void,
-            out_data.len() as i32,
-            in_data.as_ptr() as *mut core::ffi::c_void,
-            in_len.map(|v| v as i32).unwrap_or(-1),
+            out_len,
+            in_data.as_mut_ptr() as *mut core::ffi::c_void,
+            in_len_raw,
         )
-    }
+    };
+    transaction_result(OP, result, in_len_raw)
 }
 
-/// Rust wrapper for [ffi::csp_transaction_w_opts].
-///
-/// # Parameters
-///
-/// * `in_len`: Use [None] if the length is unknown, and the expected reply length otherwise.
-///
-/// # Returns
-///
-/// 1 or reply size on success, 0 otherwise.
-#[allow(clippy::too_many_arguments)]
-pub fn csp_transaction_w_opts(
-    prio: MsgPriority,
-    dst: u16,
-    dst_port: u8,
-    timeout: Duration,
-    out_data: &[u8],
-    in_data: &mut [u8],
-    in_len: Option<usize>,
-    opts: ConnectOpts,
-) -> i32 {
-    unsafe {
-        ffi::csp_transaction_w_opts(
-            prio as u8,
-            dst,
-            dst_port,
-            timeout.as_millis() as u32,
-            out_data.as_ptr() as *const core::ffi::c_void,
-            out_data.len() as i32,
-            in_data.as_ptr() as *mut core::ffi::c_void,
-            in_len.map(|v| v as i32).unwrap_or(-1),
-            opts.bits(),
-        )
+/// Checks that the request of a transaction fits into a packet and returns its raw length for the
+/// transaction API.
+fn transaction_out_len(op: &'static str, out_data: &[u8]) -> Result<i32, CspError> {
+    if out_data.len() > ffi::CSP_BUFFER_SIZE {
+        return Err(CspError::new(op, ErrorCode::Inval));
     }
+    Ok(out_data.len() as i32)
 }
 
-/// Calls [csp_transaction_w_opts] with [ConnectOpts::NONE].
-pub fn csp_transaction(
-    prio: MsgPriority,
-    dst: u16,
-    dst_port: u8,
-    timeout: Duration,
-    out_data: &[u8],
-    in_data: &mut [u8],
+/// Checks that the reply of a transaction fits into `in_data` and returns the raw expected reply
+/// length for the transaction API.
+fn transaction_in_len(
+    op: &'static str,
+    in_data: &[u8],
     in_len: Option<usize>,
-) -> i32 {
-    csp_transaction_w_opts(
-        prio,
-        dst,
-        dst_port,
-        timeout,
-        out_data,
-        in_data,
-        in_len,
-        ConnectOpts::NONE,
-    )
-}
-
-pub mod udp {
-    use super::*;
+) -> Result<i32, CspError> {
+    match in_len {
+        Some(len) if len <= in_data.len() && len <= ffi::CSP_BUFFER_SIZE => Ok(len as i32),
+        None if in_data.len() >= ffi::CSP_BUFFER_SIZE => Ok(-1),
+        _ => Err(CspError::new(op, ErrorCode::Inval)),
+    }
+}
 
-    /// Rust wrapper for [ffi::udp::csp_if_udp_init].
-    pub fn csp_if_udp_init(iface: &mut CspInterface, ifconf: &mut CspUdpConf) {
-        unsafe { ffi::udp::csp_if_udp_init(&mut iface.0, &mut ifconf.0) }
+/// Converts the result of the transaction API. `libcsp` returns 0 on failure, 1 if no reply was
+/// expected and the reply length otherwise.
+fn transaction_result(op: &'static str, result: i32, in_len_raw: i32) -> Result<usize, CspError> {
+    if result <= 0 {
+        return Err(CspError::new(op, ErrorCode::TimedOut));
     }
+    if in_len_raw == 0 {
+        return Ok(0);
+    }
+    Ok(result as usize)
 }
 
-pub mod iflist {
+#[cfg(test)]
+mod tests {
     use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn error_code_conversion() {
+        // CSP_ERR_USED, returned by csp_bind if the port is already bound.
+        assert_eq!(ErrorCode::from(-4), ErrorCode::Used);
+        assert_eq!(i32::from(ErrorCode::Sfp), -103);
+        assert_eq!(ErrorCode::from(-55), ErrorCode::Unknown(-55));
+        assert_eq!(i32::from(ErrorCode::Unknown(-55)), -55);
+    }
 
-    /// Rust wrapper for [ffi::iflist::csp_iflist_print].
-    pub fn csp_iflist_print() {
-        // SAFETY: FFI call.
-        unsafe { ffi::iflist::csp_iflist_print() }
+    #[test]
+    fn error_check() {
+        assert!(CspError::check("csp_bind", 0).is_ok());
+        let error = CspError::check("csp_bind", -4).unwrap_err();
+        assert_eq!(error.op(), "csp_bind");
+        assert_eq!(error.code(), ErrorCode::Used);
+        assert_eq!(
+            error.to_string(),
+            "csp_bind failed: resource already in use"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn socket_storage() {
+        let storage = SocketStorage::new_boxed(SocketFlags::CONN_LESS);
+        // SAFETY: The storage is valid until it is dropped.
+        assert_eq!(
+            unsafe { (*storage.as_ptr()).opts },
+            SocketFlags::CONN_LESS.bits()
+        );
+        drop(storage);
+
+        let raw: &'static mut CspSocket = std::boxed::Box::leak(std::boxed::Box::default());
+        raw.0.opts = u32::MAX;
+        raw.0.rx_queue = core::ptr::NonNull::dangling().as_ptr();
+        let ptr: *mut CspSocket = raw;
+        let storage = SocketStorage::new_static(raw, SocketFlags::RDPREQ);
+        // The static storage is used in place and reset.
+        assert_eq!(storage.as_ptr(), ptr as *mut csp_socket_s);
+        // SAFETY: The storage is valid.
+        let inner = unsafe { &*storage.as_ptr() };
+        assert_eq!(inner.opts, SocketFlags::RDPREQ.bits());
+        assert!(inner.rx_queue.is_null());
+        drop(storage);
+
+        // The static storage is not freed on drop, so it can be used again.
+        // SAFETY: The previous storage using the memory was dropped.
+        let storage = SocketStorage::new_static(unsafe { &mut *ptr }, SocketFlags::NONE);
+        // SAFETY: The storage is valid.
+        assert_eq!(unsafe { (*storage.as_ptr()).opts }, 0);
+        drop(storage);
+        // SAFETY: The memory was leaked from a box above and is not used anymore.
+        drop(unsafe { std::boxed::Box::from_raw(ptr) });
+    }
+
+    #[test]
+    fn datagram_arguments() {
+        let id = CspId {
+            prio: MsgPriority::High,
+            flags: HeaderFlags::empty(),
+            src: 12,
+            dst: 3,
+            dport: 10,
+            sport: 42,
+        };
+        assert_eq!(CspEndpoint::source(&id), CspEndpoint { addr: 12, port: 42 });
+        assert_eq!(reply_opts(SocketFlags::CONN_LESS), ConnectOpts::NONE);
+        assert_eq!(
+            reply_opts(SocketFlags::CONN_LESS | SocketFlags::SAME),
+            ConnectOpts::SAME
+        );
+    }
+
+    #[test]
+    fn transaction_lengths() {
+        let buf = [0; ffi::CSP_BUFFER_SIZE + 1];
+        assert_eq!(
+            transaction_out_len("op", &buf[..ffi::CSP_BUFFER_SIZE]),
+            Ok(ffi::CSP_BUFFER_SIZE as i32)
+        );
+        assert_eq!(
+            transaction_out_len("op", &buf).unwrap_err().code(),
+            ErrorCode::Inval
+        );
+        assert_eq!(transaction_result("op", 12, -1), Ok(12));
+        assert_eq!(transaction_result("op", 1, 0), Ok(0));
+        assert_eq!(transaction_result("op", 4, 4), Ok(4));
+        for (result, in_len_raw) in [(-1, -1), (0, -1), (0, 0), (0, 4)] {
+            assert_eq!(
+                transaction_result("op", result, in_len_raw)
+                    .unwrap_err()
+                    .code(),
+                ErrorCode::TimedOut
+            );
+        }
+    }
+
+    #[test]
+    fn packet_length_clamped() {
+        // SAFETY: The packet only consists of integers and integer arrays.
+        let mut raw: std::boxed::Box<csp_packet_s> =
+            std::boxed::Box::new(unsafe { core::mem::zeroed() });
+        // The packet is not allocated from the buffer pool, so it must not be dropped.
+        // SAFETY: The pointer is valid and not used anywhere else while the packet exists.
+        let mut packet =
+            core::mem::ManuallyDrop::new(unsafe { Packet::from_raw(&mut *raw) }.unwrap());
+        packet.set_data(b"abc").unwrap();
+        assert_eq!(&**packet, b"abc");
+        assert_eq!(packet.remaining(), Packet::CAPACITY - 3);
+        packet.inner_mut().length = u16::MAX;
+        assert_eq!(packet.len(), Packet::CAPACITY);
+        assert_eq!(packet.remaining(), 0);
+        assert_eq!(packet.len(), (**packet).len());
     }
 
-    /// Rust wrapper for [ffi::iflist::csp_iflist_add].
-    pub fn csp_iflist_add(iface: &mut CspInterface) -> i32 {
-        unsafe { ffi::iflist::csp_iflist_add(&mut iface.0) }
+    #[test]
+    fn csp_id_conversion() {
+        let id = CspId {
+            prio: MsgPriority::High,
+            flags: HeaderFlags::RDP | HeaderFlags::CRC32,
+            src: 1,
+            dst: 2,
+            dport: 10,
+            sport: 20,
+        };
+        let raw = ffi::csp_id_t::from(id);
+        assert_eq!(raw.pri, 1);
+        assert_eq!(raw.flags, 0x03);
+        assert_eq!(CspId::from(raw), id);
     }
 }
@@ -0,0 +1,103 @@
+//! Promiscuous mode of the CSP router.
+//!
+//! In promiscuous mode, the router places a copy of every routed packet into a separate queue,
+//! independently of its destination. The [Sniffer] handle gives access to this queue.
+use core::{
+    ffi::c_uint,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use crate::{ffi, timeout_millis, CspError, CspStack, ErrorCode, Packet};
+
+static SNIFFER_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Queue sizes which are too large for `libcsp` are clamped to the largest possible size.
+fn queue_len(queue_size: usize) -> c_uint {
+    c_uint::try_from(queue_size).unwrap_or(c_uint::MAX)
+}
+
+/// Handle for the promiscuous mode. Promiscuous mode is disabled when the sniffer is dropped.
+///
+/// `libcsp` only has a single promiscuous queue, so only one sniffer can exist at a time.
+#[derive(Debug)]
+pub struct Sniffer {
+    _priv: (),
+}
+
+impl Sniffer {
+    /// Rust wrapper for [ffi::promisc::csp_promisc_enable]. `queue_size` is the maximum number
+    /// of packets in the promiscuous queue. It is only used when promiscuous mode is enabled for
+    /// the first time, because `libcsp` keeps the queue afterwards.
+    ///
+    /// Returns [ErrorCode::Busy] if another sniffer exists.
+    pub fn new(_stack: &CspStack, queue_size: usize) -> Result<Self, CspError> {
+        if SNIFFER_ACTIVE.swap(true, Ordering::AcqRel) {
+            return Err(CspError::new("csp_promisc_enable", ErrorCode::Busy));
+        }
+        // SAFETY: FFI call.
+        let result = unsafe { ffi::promisc::csp_promisc_enable(queue_len(queue_size)) };
+        if let Err(e) = CspError::check("csp_promisc_enable", result) {
+            SNIFFER_ACTIVE.store(false, Ordering::Release);
+            return Err(e);
+        }
+        Ok(Self { _priv: () })
+    }
+
+    /// Rust wrapper for [ffi::promisc::csp_promisc_read].
+    ///
+    /// Returns [ErrorCode::TimedOut] if no packet arrived in time.
+    pub fn read(&self, timeout: Duration) -> Result<Packet, CspError> {
+        // SAFETY: FFI call. The ownership of the packet copy is passed to us.
+        unsafe { Packet::from_raw(ffi::promisc::csp_promisc_read(timeout_millis(timeout))) }
+            .ok_or(CspError::new("csp_promisc_read", ErrorCode::TimedOut))
+    }
+
+    /// Blocking iterator over the sniffed packets. Each call to [Iterator::next] waits up to
+    /// `timeout` for a new packet, and the iteration ends if no packet arrives in that time.
+    pub fn packets(&self, timeout: Duration) -> Packets<'_> {
+        Packets {
+            sniffer: self,
+            timeout,
+        }
+    }
+}
+
+impl Drop for Sniffer {
+    fn drop(&mut self) {
+        // SAFETY: FFI call.
+        unsafe { ffi::promisc::csp_promisc_disable() };
+        // Return the queued packet copies to the buffer pool.
+        while let Ok(packet) = self.read(Duration::ZERO) {
+            drop(packet);
+        }
+        SNIFFER_ACTIVE.store(false, Ordering::Release);
+    }
+}
+
+/// Iterator over the packets of a [Sniffer], created by [Sniffer::packets].
+pub struct Packets<'sniffer> {
+    sniffer: &'sniffer Sniffer,
+    timeout: Duration,
+}
+
+impl Iterator for Packets<'_> {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.sniffer.read(self.timeout).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_size_clamped() {
+        assert_eq!(queue_len(0), 0);
+        assert_eq!(queue_len(10), 10);
+        assert_eq!(queue_len(c_uint::MAX as usize), c_uint::MAX);
+        assert_eq!(queue_len(usize::MAX), c_uint::MAX);
+    }
+}
@@ -0,0 +1,182 @@
+//! Reliable Datagram Protocol (RDP) configuration and diagnostics.
+//!
+//! The global [RdpOptions] are used by the connecting side. When an RDP connection is
+//! established, the client transmits its options to the server, so the options of a connection
+//! can be inspected with [conn_state] on both sides.
+use core::{ffi::c_uint, ptr::addr_of, time::Duration};
+
+use crate::{
+    ffi, timeout_millis, ConnectOpts, CspConnRef, CspError, CspStack, ErrorCode, RdpState,
+};
+
+/// RDP options, see [ffi::csp_rdp_set_opt].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RdpOptions {
+    /// Maximum number of unacknowledged packets. At most [ffi::CSP_RDP_MAX_WINDOW].
+    pub window_size: u32,
+    /// Timeout for establishing a connection and for idle connections.
+    pub conn_timeout: Duration,
+    /// Timeout after which an unacknowledged packet is retransmitted.
+    pub packet_timeout: Duration,
+    /// Acknowledge several packets at once instead of every packet.
+    pub delayed_acks: bool,
+    /// Maximum delay of an acknowledgement if delayed acknowledgements are enabled.
+    pub ack_timeout: Duration,
+    /// Send an acknowledgement after this number of packets if delayed acknowledgements are
+    /// enabled.
+    pub ack_delay_count: u32,
+}
+
+fn check_options(options: &RdpOptions) -> Result<(), CspError> {
+    if options.window_size == 0 || options.window_size as usize > ffi::CSP_RDP_MAX_WINDOW {
+        return Err(CspError::new("csp_rdp_set_opt", ErrorCode::Inval));
+    }
+    Ok(())
+}
+
+/// Rust wrapper for [ffi::csp_rdp_get_opt].
+pub fn options(_stack: &CspStack) -> RdpOptions {
+    let mut window_size: c_uint = 0;
+    let mut conn_timeout: c_uint = 0;
+    let mut packet_timeout: c_uint = 0;
+    let mut delayed_acks: c_uint = 0;
+    let mut ack_timeout: c_uint = 0;
+    let mut ack_delay_count: c_uint = 0;
+    // SAFETY: FFI call with valid output pointers.
+    unsafe {
+        ffi::csp_rdp_get_opt(
+            &mut window_size,
+            &mut conn_timeout,
+            &mut packet_timeout,
+            &mut delayed_acks,
+            &mut ack_timeout,
+            &mut ack_delay_count,
+        )
+    };
+    RdpOptions {
+        window_size,
+        conn_timeout: Duration::from_millis(conn_timeout.into()),
+        packet_timeout: Duration::from_millis(packet_timeout.into()),
+        delayed_acks: delayed_acks != 0,
+        ack_timeout: Duration::from_millis(ack_timeout.into()),
+        ack_delay_count,
+    }
+}
+
+/// Rust wrapper for [ffi::csp_rdp_set_opt]. The options apply to connections which are
+/// established afterwards. Timeouts are truncated to milliseconds.
+///
+/// Returns [ErrorCode::Inval] if the window size is 0 or larger than
+/// [ffi::CSP_RDP_MAX_WINDOW].
+pub fn set_options(_stack: &CspStack, options: &RdpOptions) -> Result<(), CspError> {
+    check_options(options)?;
+    // SAFETY: FFI call.
+    unsafe {
+        ffi::csp_rdp_set_opt(
+            options.window_size,
+            timeout_millis(options.conn_timeout),
+            timeout_millis(options.packet_timeout),
+            options.delayed_acks.into(),
+            timeout_millis(options.ack_timeout),
+            options.ack_delay_count,
+        )
+    };
+    Ok(())
+}
+
+/// Snapshot of the RDP state of a connection, see [ffi::csp_rdp_t].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct RdpConnState {
+    pub state: RdpState,
+    /// Tracks which side has closed the connection.
+    pub closed_by: u8,
+    /// Sequence number of the next segment to send.
+    pub snd_nxt: u16,
+    /// Sequence number of the oldest unacknowledged segment.
+    pub snd_una: u16,
+    /// Initial send sequence number.
+    pub snd_iss: u16,
+    /// Sequence number of the last segment received correctly and in sequence.
+    pub rcv_cur: u16,
+    /// Initial receive sequence number.
+    pub rcv_irs: u16,
+    /// Last sequence number acknowledged to the sender.
+    pub rcv_lsa: u16,
+    /// Options negotiated for the connection.
+    pub options: RdpOptions,
+}
+
+impl RdpConnState {
+    /// Number of sent segments which are not acknowledged yet.
+    pub fn unacked(&self) -> u16 {
+        self.snd_nxt.wrapping_sub(self.snd_una)
+    }
+}
+
+/// Read the RDP state of a connection for diagnostics.
+///
+/// Returns [None] if the connection does not use RDP or has an unknown state. The state is
+/// updated by the router task, so the fields of the snapshot may belong to slightly different
+/// points in time.
+pub fn conn_state(conn: &CspConnRef) -> Option<RdpConnState> {
+    let conn = conn.0;
+    if conn.is_null() {
+        return None;
+    }
+    // SAFETY: The connection pointer is valid, and connections are never freed. Volatile
+    // reads, because the fields are modified by the router task.
+    unsafe {
+        let opts = addr_of!((*conn).opts).read_volatile();
+        if opts & ConnectOpts::RDP.bits() == 0 {
+            return None;
+        }
+        let rdp = addr_of!((*conn).rdp);
+        let state = RdpState::try_from(addr_of!((*rdp).state).read_volatile()).ok()?;
+        let millis = |ms: *const u32| Duration::from_millis(ms.read_volatile().into());
+        Some(RdpConnState {
+            state,
+            closed_by: addr_of!((*rdp).closed_by).read_volatile(),
+            snd_nxt: addr_of!((*rdp).snd_nxt).read_volatile(),
+            snd_una: addr_of!((*rdp).snd_una).read_volatile(),
+            snd_iss: addr_of!((*rdp).snd_iss).read_volatile(),
+            rcv_cur: addr_of!((*rdp).rcv_cur).read_volatile(),
+            rcv_irs: addr_of!((*rdp).rcv_irs).read_volatile(),
+            rcv_lsa: addr_of!((*rdp).rcv_lsa).read_volatile(),
+            options: RdpOptions {
+                window_size: addr_of!((*rdp).window_size).read_volatile(),
+                conn_timeout: millis(addr_of!((*rdp).conn_timeout)),
+                packet_timeout: millis(addr_of!((*rdp).packet_timeout)),
+                delayed_acks: addr_of!((*rdp).delayed_acks).read_volatile() != 0,
+                ack_timeout: millis(addr_of!((*rdp).ack_timeout)),
+                ack_delay_count: addr_of!((*rdp).ack_delay_count).read_volatile(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_validation() {
+        let mut options = RdpOptions {
+            window_size: 1,
+            conn_timeout: Duration::from_secs(10),
+            packet_timeout: Duration::from_secs(1),
+            delayed_acks: true,
+            ack_timeout: Duration::from_millis(250),
+            ack_delay_count: 2,
+        };
+        assert!(check_options(&options).is_ok());
+        options.window_size = ffi::CSP_RDP_MAX_WINDOW as u32;
+        assert!(check_options(&options).is_ok());
+        for window_size in [0, ffi::CSP_RDP_MAX_WINDOW as u32 + 1] {
+            options.window_size = window_size;
+            assert_eq!(
+                check_options(&options).unwrap_err().code(),
+                ErrorCode::Inval
+            );
+        }
+    }
+}
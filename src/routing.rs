@@ -0,0 +1,332 @@
+//! Routing table API.
+//!
+//! The routing table is only available if `libcsp` was built with the routing table enabled,
+//! for example by setting the `rtable` field of the `libcsp-cargo-build` configuration.
+//!
+//! Routes use the `libcsp` string format `<address>[/<netmask>] <interface> [<via>]`, and
+//! multiple routes are separated by commas, for example `"0/0 CAN, 8 KISS, 10 I2C 10"`. The
+//! netmask is given as the number of bits of the address which must match.
+use alloc::{
+    ffi::CString,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    ffi::{c_int, c_void},
+    fmt::{self, Display, Write},
+    str::FromStr,
+};
+
+use crate::{ffi, iface_name, CspError, CspStack, ErrorCode};
+
+/// Entry of the routing table.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Route {
+    /// Destination address.
+    pub dest: u16,
+    /// Number of bits of the netmask. [None] uses all bits of the address.
+    pub netmask: Option<u8>,
+    /// Name of the interface used for the route.
+    pub iface: String,
+    /// Address of the next hop, if it is not the destination itself.
+    pub via: Option<u16>,
+}
+
+impl Route {
+    pub fn new(dest: u16, netmask: Option<u8>, iface: impl Into<String>, via: Option<u16>) -> Self {
+        Self {
+            dest,
+            netmask,
+            iface: iface.into(),
+            via,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The route must be a valid entry of the `libcsp` routing table.
+    unsafe fn from_raw(route: &ffi::csp_route_t) -> Self {
+        Self {
+            dest: route.address,
+            netmask: Some(route.netmask as u8),
+            // SAFETY: Routes reference valid interfaces.
+            iface: unsafe { iface_name(route.iface) }.to_string(),
+            via: (route.via as u32 != ffi::CSP_NO_VIA_ADDRESS).then_some(route.via),
+        }
+    }
+}
+
+impl Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.dest)?;
+        if let Some(netmask) = self.netmask {
+            write!(f, "/{}", netmask)?;
+        }
+        write!(f, " {}", self.iface)?;
+        if let Some(via) = self.via {
+            write!(f, " {}", via)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when parsing a [Route] fails.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseRouteError {
+    /// The route does not contain an interface.
+    MissingInterface,
+    InvalidAddress(String),
+    InvalidNetmask(String),
+    InvalidVia(String),
+    /// The route contains unexpected trailing fields.
+    TrailingData(String),
+}
+
+impl Display for ParseRouteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRouteError::MissingInterface => write!(f, "route has no interface"),
+            ParseRouteError::InvalidAddress(s) => write!(f, "invalid route address {:?}", s),
+            ParseRouteError::InvalidNetmask(s) => write!(f, "invalid route netmask {:?}", s),
+            ParseRouteError::InvalidVia(s) => write!(f, "invalid route via address {:?}", s),
+            ParseRouteError::TrailingData(s) => write!(f, "unexpected route data {:?}", s),
+        }
+    }
+}
+
+impl core::error::Error for ParseRouteError {}
+
+impl FromStr for Route {
+    type Err = ParseRouteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let dest_field = fields.next().ok_or(ParseRouteError::MissingInterface)?;
+        let (dest, netmask) = match dest_field.split_once('/') {
+            Some((dest, netmask)) => (
+                dest,
+                Some(
+                    netmask
+                        .parse()
+                        .map_err(|_| ParseRouteError::InvalidNetmask(netmask.to_string()))?,
+                ),
+            ),
+            None => (dest_field, None),
+        };
+        let dest = dest
+            .parse()
+            .map_err(|_| ParseRouteError::InvalidAddress(dest.to_string()))?;
+        let iface = fields.next().ok_or(ParseRouteError::MissingInterface)?;
+        let via = fields
+            .next()
+            .map(|via| {
+                via.parse()
+                    .map_err(|_| ParseRouteError::InvalidVia(via.to_string()))
+            })
+            .transpose()?;
+        if let Some(trailing) = fields.next() {
+            return Err(ParseRouteError::TrailingData(trailing.to_string()));
+        }
+        Ok(Self::new(dest, netmask, iface, via))
+    }
+}
+
+/// Parse a routing table in the `libcsp` string format. Empty entries are ignored.
+pub fn parse_table(table: &str) -> Result<Vec<Route>, ParseRouteError> {
+    table
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Serialize routes into the `libcsp` string format.
+pub fn format_table<'a>(routes: impl IntoIterator<Item = &'a Route>) -> String {
+    let mut table = String::new();
+    for (i, route) in routes.into_iter().enumerate() {
+        if i > 0 {
+            table.push_str(", ");
+        }
+        // Writing to a string can not fail.
+        let _ = write!(table, "{}", route);
+    }
+    table
+}
+
+/// Look up an interface of the interface list by name.
+fn find_iface(op: &'static str, name: &str) -> Result<*mut ffi::csp_iface_t, CspError> {
+    let unknown_iface = CspError::new(op, ErrorCode::Inval);
+    let name = CString::new(name).map_err(|_| unknown_iface)?;
+    // SAFETY: FFI call.
+    let iface = unsafe { ffi::iflist::csp_iflist_get_by_name(name.as_ptr()) };
+    if iface.is_null() {
+        return Err(unknown_iface);
+    }
+    Ok(iface)
+}
+
+/// Rust wrapper for [ffi::rtable::csp_rtable_set]. Existing routes for the same destination
+/// and netmask are replaced.
+///
+/// Returns [ErrorCode::Inval] if no interface with the name of the route was added with
+/// [crate::iflist::csp_iflist_add].
+pub fn insert(_stack: &CspStack, route: &Route) -> Result<(), CspError> {
+    let iface = find_iface("csp_rtable_set", &route.iface)?;
+    let netmask = route.netmask.map(c_int::from).unwrap_or(-1);
+    let via = route.via.unwrap_or(ffi::CSP_NO_VIA_ADDRESS as u16);
+    // SAFETY: FFI call. Interfaces in the interface list are valid for the program lifetime.
+    let result = unsafe { ffi::rtable::csp_rtable_set(route.dest, netmask, iface, via) };
+    CspError::check("csp_rtable_set", result)
+}
+
+/// Parse a routing table in the `libcsp` string format and insert all routes. All routes are
+/// checked before the first route is inserted. Returns the number of inserted routes.
+///
+/// Returns [ErrorCode::Inval] if the table is invalid or references an unknown interface.
+pub fn load(stack: &CspStack, table: &str) -> Result<usize, CspError> {
+    let routes =
+        parse_table(table).map_err(|_| CspError::new("csp_rtable_load", ErrorCode::Inval))?;
+    for route in &routes {
+        find_iface("csp_rtable_load", &route.iface)?;
+    }
+    for route in &routes {
+        insert(stack, route)?;
+    }
+    Ok(routes.len())
+}
+
+/// Rust wrapper for [ffi::rtable::csp_rtable_find_route]. Returns the best matching route for
+/// a destination address.
+pub fn lookup(_stack: &CspStack, dest: u16) -> Option<Route> {
+    // SAFETY: FFI call. The returned route is an entry of the routing table.
+    let route = unsafe { ffi::rtable::csp_rtable_find_route(dest).as_ref() }?;
+    // SAFETY: The route is an entry of the routing table.
+    Some(unsafe { Route::from_raw(route) })
+}
+
+/// Trampoline for [ffi::rtable::csp_rtable_iterate], whose context is a closure of type `F`.
+/// A panicking closure stops the iteration.
+unsafe extern "C" fn iterator<F: FnMut(Route) -> bool>(
+    ctx: *mut c_void,
+    route: *mut ffi::csp_route_t,
+) -> bool {
+    // SAFETY: libcsp passes valid routes.
+    let Some(route) = (unsafe { route.as_ref() }) else {
+        return true;
+    };
+    // SAFETY: The context is the closure passed to csp_rtable_iterate, and the route is an
+    // entry of the routing table.
+    let (f, route) = unsafe { (&mut *(ctx as *mut F), Route::from_raw(route)) };
+    #[cfg(feature = "std")]
+    return std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(route))).unwrap_or(false);
+    #[cfg(not(feature = "std"))]
+    f(route)
+}
+
+/// Rust wrapper for [ffi::rtable::csp_rtable_iterate]. The closure is invoked for every route
+/// and can return [false] to stop the iteration. The iteration is stopped as well if the closure
+/// panics.
+pub fn for_each<F: FnMut(Route) -> bool>(_stack: &CspStack, mut f: F) {
+    // SAFETY: FFI call. The closure outlives the iteration.
+    unsafe { ffi::rtable::csp_rtable_iterate(Some(iterator::<F>), &mut f as *mut F as *mut c_void) }
+}
+
+/// All entries of the routing table.
+pub fn routes(stack: &CspStack) -> Vec<Route> {
+    let mut routes = Vec::new();
+    for_each(stack, |route| {
+        routes.push(route);
+        true
+    });
+    routes
+}
+
+/// Serialize the routing table into the `libcsp` string format.
+pub fn save(stack: &CspStack) -> String {
+    format_table(&routes(stack))
+}
+
+/// Rust wrapper for [ffi::rtable::csp_rtable_clear]. Only the loopback route remains.
+pub fn clear(_stack: &CspStack) {
+    // SAFETY: FFI call.
+    unsafe { ffi::rtable::csp_rtable_clear() }
+}
+
+/// Rust wrapper for [ffi::rtable::csp_rtable_print].
+pub fn print(_stack: &CspStack) {
+    // SAFETY: FFI call.
+    unsafe { ffi::rtable::csp_rtable_print() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_route() {
+        assert_eq!(
+            "10/5 I2C 10".parse::<Route>().unwrap(),
+            Route::new(10, Some(5), "I2C", Some(10))
+        );
+        assert_eq!(
+            "8 KISS".parse::<Route>().unwrap(),
+            Route::new(8, None, "KISS", None)
+        );
+        assert_eq!("".parse::<Route>(), Err(ParseRouteError::MissingInterface));
+        assert_eq!("8".parse::<Route>(), Err(ParseRouteError::MissingInterface));
+        assert_eq!(
+            "x/5 CAN".parse::<Route>(),
+            Err(ParseRouteError::InvalidAddress("x".into()))
+        );
+        assert_eq!(
+            "1/y CAN".parse::<Route>(),
+            Err(ParseRouteError::InvalidNetmask("y".into()))
+        );
+        assert_eq!(
+            "1 CAN z".parse::<Route>(),
+            Err(ParseRouteError::InvalidVia("z".into()))
+        );
+        assert_eq!(
+            "1 CAN 2 3".parse::<Route>(),
+            Err(ParseRouteError::TrailingData("3".into()))
+        );
+    }
+
+    #[test]
+    fn table_round_trip() {
+        let table = "0/0 CAN, 8 KISS, 10/5 I2C 10";
+        let routes = parse_table(table).unwrap();
+        assert_eq!(routes.len(), 3);
+        assert_eq!(routes[0], Route::new(0, Some(0), "CAN", None));
+        assert_eq!(format_table(&routes), table);
+        assert_eq!(parse_table(" , ").unwrap(), Vec::new());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn iterator_catches_panics() {
+        fn call<F: FnMut(Route) -> bool>(f: &mut F, route: *mut ffi::csp_route_t) -> bool {
+            // SAFETY: The context is a closure of the type of the trampoline.
+            unsafe { iterator::<F>(f as *mut F as *mut c_void, route) }
+        }
+        // SAFETY: The route only consists of integers and a null interface pointer.
+        let mut route: ffi::csp_route_t = unsafe { core::mem::zeroed() };
+        route.address = 8;
+        route.netmask = 14;
+        route.via = ffi::CSP_NO_VIA_ADDRESS as u16;
+
+        let mut seen = Vec::new();
+        assert!(call(
+            &mut |route| {
+                seen.push(route);
+                true
+            },
+            &mut route
+        ));
+        assert_eq!(seen, [Route::new(8, Some(14), "", None)]);
+
+        let mut panicking = |_: Route| -> bool { panic!("route closure panicked") };
+        assert!(!call(&mut panicking, &mut route));
+        assert!(call(&mut panicking, core::ptr::null_mut()));
+    }
+}
@@ -0,0 +1,509 @@
+//! Client and server API for the standard CSP services.
+//!
+//! These services are answered by [crate::csp_service_handler] on the remote node. All requests
+//! which expect a reply return [ErrorCode::TimedOut] if the node did not answer in time.
+//!
+//! A node can answer the services on the reserved ports in Rust by implementing
+//! [ServiceHandler] and passing received packets to [dispatch]. With the `std` feature, the
+//! handler can also be installed globally with [install], which makes
+//! [crate::csp_service_handler] use it.
+use core::time::Duration;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::{
+    csp_read, csp_send, ffi, timeout_millis, ConnectOpts, CspError, CspStack, ErrorCode,
+    MsgPriority, Packet, ReservedPort, SocketFlags,
+};
+
+/// Uptime of a node.
+pub fn uptime(_stack: &CspStack, node: u16, timeout: Duration) -> Result<Duration, CspError> {
+    let mut uptime: u32 = 0;
+    // SAFETY: FFI call.
+    let result = unsafe { ffi::csp_get_uptime(node, timeout_millis(timeout), &mut uptime) };
+    CspError::check("csp_get_uptime", result)?;
+    Ok(Duration::from_secs(uptime.into()))
+}
+
+/// Free memory of a node in bytes.
+pub fn memfree(_stack: &CspStack, node: u16, timeout: Duration) -> Result<u32, CspError> {
+    let mut size: u32 = 0;
+    // SAFETY: FFI call.
+    let result = unsafe { ffi::csp_get_memfree(node, timeout_millis(timeout), &mut size) };
+    CspError::check("csp_get_memfree", result)?;
+    Ok(size)
+}
+
+/// Number of free packet buffers of a node.
+pub fn buf_free(_stack: &CspStack, node: u16, timeout: Duration) -> Result<u32, CspError> {
+    let mut size: u32 = 0;
+    // SAFETY: FFI call.
+    let result = unsafe { ffi::csp_get_buf_free(node, timeout_millis(timeout), &mut size) };
+    CspError::check("csp_get_buf_free", result)?;
+    Ok(size)
+}
+
+/// Request the process list of a node and pass every reply chunk to `output`. The request ends
+/// when the node sent the terminating NUL character or when no further reply arrived within
+/// `timeout`.
+fn ps_request(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    mut output: impl FnMut(&[u8]),
+) -> Result<(), CspError> {
+    let mut conn = stack.connect_guarded(
+        MsgPriority::Normal,
+        node,
+        ReservedPort::Ps as u8,
+        timeout,
+        ConnectOpts::CRC32,
+    )?;
+    let mut request = stack.buffer_get()?;
+    // The request payload is ignored by the service handler.
+    request.set_data(&[0x55])?;
+    csp_send(&mut conn.0, request);
+
+    let mut received = false;
+    while let Ok(reply) = csp_read(&mut conn.0, timeout) {
+        received = true;
+        match reply.iter().position(|&byte| byte == 0) {
+            Some(end) => {
+                output(&reply[..end]);
+                break;
+            }
+            None => output(&reply),
+        }
+    }
+    if !received {
+        return Err(CspError::new("csp_ps", ErrorCode::TimedOut));
+    }
+    Ok(())
+}
+
+/// Request the process list of a node and write it into the given buffer. This is the same
+/// request as [ffi::csp_ps], but the output is captured instead of printed.
+///
+/// Returns the number of bytes written. Output which does not fit into the buffer is
+/// discarded. The request ends when the node sent the terminating NUL character or when no
+/// further reply arrived within `timeout`.
+pub fn ps_into(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    buf: &mut [u8],
+) -> Result<usize, CspError> {
+    let mut written = 0;
+    ps_request(stack, node, timeout, |data| {
+        let len = core::cmp::min(data.len(), buf.len() - written);
+        buf[written..written + len].copy_from_slice(&data[..len]);
+        written += len;
+    })?;
+    Ok(written)
+}
+
+/// Request the process list of a node. Unlike [ps_into], the whole output is returned.
+#[cfg(feature = "alloc")]
+pub fn ps(stack: &CspStack, node: u16, timeout: Duration) -> Result<String, CspError> {
+    let mut output = Vec::new();
+    ps_request(stack, node, timeout, |data| output.extend_from_slice(data))?;
+    Ok(String::from_utf8(output)
+        .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned()))
+}
+
+/// Send a ping without waiting for the reply.
+pub fn ping_noreply(_stack: &CspStack, node: u16) {
+    // SAFETY: FFI call.
+    unsafe { ffi::csp_ping_noreply(node) }
+}
+
+/// Send a ping and wait for the reply. See [CspStack::ping].
+pub fn ping(
+    stack: &CspStack,
+    node: u16,
+    timeout: Duration,
+    size: usize,
+    opts: SocketFlags,
+) -> Result<Duration, CspError> {
+    stack.ping(node, timeout, size, opts)
+}
+
+/// Reboot a node. See [CspStack::reboot].
+pub fn reboot(stack: &CspStack, node: u16) {
+    stack.reboot(node)
+}
+
+/// Shut down a node. The request is not acknowledged.
+pub fn shutdown(_stack: &CspStack, node: u16) {
+    // SAFETY: FFI call.
+    unsafe { ffi::csp_shutdown(node) }
+}
+
+/// Rust implementation of the services on the reserved ports.
+///
+/// Every method answers the request on its [ReservedPort]. The default implementations return
+/// [ErrorCode::NotSup], in which case the request is passed on to the C implementation
+/// [ffi::csp_service_handler]. Requests for which another error is returned are dropped without
+/// a reply. Requests on other ports, including [ReservedPort::Cmp], are always handled by the C
+/// implementation.
+pub trait ServiceHandler {
+    /// Return [Ok] to echo the ping request.
+    fn ping(&mut self, _request: &Packet) -> Result<(), CspError> {
+        Err(CspError::new("csp_service_handler", ErrorCode::NotSup))
+    }
+
+    /// Write the process list into the reply packet. A terminating NUL character is appended
+    /// if there is space left.
+    fn ps(&mut self, _reply: &mut Packet) -> Result<(), CspError> {
+        Err(CspError::new("csp_service_handler", ErrorCode::NotSup))
+    }
+
+    /// Free memory in bytes.
+    fn memfree(&mut self) -> Result<u32, CspError> {
+        Err(CspError::new("csp_service_handler", ErrorCode::NotSup))
+    }
+
+    /// Reboot the node. This is only invoked if the request contains
+    /// [ffi::CSP_REBOOT_MAGIC].
+    fn reboot(&mut self) -> Result<(), CspError> {
+        Err(CspError::new("csp_service_handler", ErrorCode::NotSup))
+    }
+
+    /// Shut down the node. This request is received on the [ReservedPort::Reboot] port and is
+    /// only invoked if the request contains [ffi::CSP_REBOOT_SHUTDOWN_MAGIC].
+    fn shutdown(&mut self) -> Result<(), CspError> {
+        Err(CspError::new("csp_service_handler", ErrorCode::NotSup))
+    }
+
+    /// Number of free packet buffers.
+    fn buf_free(&mut self) -> Result<u32, CspError> {
+        Err(CspError::new("csp_service_handler", ErrorCode::NotSup))
+    }
+
+    fn uptime(&mut self) -> Result<Duration, CspError> {
+        Err(CspError::new("csp_service_handler", ErrorCode::NotSup))
+    }
+}
+
+fn reply_u32(packet: &mut Packet, value: u32) {
+    // Can not fail, the packet can always hold 4 bytes.
+    let _ = packet.set_data(&value.to_be_bytes());
+}
+
+/// What [dispatch] does with a request after the handler was invoked.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Outcome {
+    /// Send the packet back as the reply.
+    Reply,
+    /// Drop the request without a reply.
+    Drop,
+    /// Pass the request on to [ffi::csp_service_handler].
+    Fallback,
+}
+
+/// Invoke the handler for a request and write the reply into the packet.
+fn handle(handler: &mut (impl ServiceHandler + ?Sized), packet: &mut Packet) -> Outcome {
+    const PING: u8 = ReservedPort::Ping as u8;
+    const PS: u8 = ReservedPort::Ps as u8;
+    const MEMFREE: u8 = ReservedPort::Memfree as u8;
+    const REBOOT: u8 = ReservedPort::Reboot as u8;
+    const BUF_FREE: u8 = ReservedPort::BufFree as u8;
+    const UPTIME: u8 = ReservedPort::Uptime as u8;
+
+    let handled = match packet.id().dport {
+        PING => handler.ping(packet),
+        PS => {
+            packet.clear();
+            let handled = handler.ps(packet);
+            if handled.is_ok() && packet.remaining() > 0 {
+                // Can not fail, there is space left.
+                let _ = packet.extend_from_slice(&[0]);
+            }
+            handled
+        }
+        MEMFREE => handler.memfree().map(|free| reply_u32(packet, free)),
+        BUF_FREE => handler.buf_free().map(|free| reply_u32(packet, free)),
+        UPTIME => handler
+            .uptime()
+            .map(|uptime| reply_u32(packet, uptime.as_secs() as u32)),
+        REBOOT => {
+            let magic = packet
+                .get(..4)
+                .map(|magic| u32::from_be_bytes(magic.try_into().unwrap()));
+            let handled = match magic {
+                Some(ffi::CSP_REBOOT_MAGIC) => handler.reboot(),
+                Some(ffi::CSP_REBOOT_SHUTDOWN_MAGIC) => handler.shutdown(),
+                // Invalid requests are dropped, like in the C implementation.
+                _ => Ok(()),
+            };
+            if handled.is_ok() {
+                // Reboot requests are not answered.
+                return Outcome::Drop;
+            }
+            handled
+        }
+        _ => Err(CspError::new("csp_service_handler", ErrorCode::NotSup)),
+    };
+    match handled {
+        Ok(()) => Outcome::Reply,
+        Err(e) if e.code() == ErrorCode::NotSup => Outcome::Fallback,
+        Err(_) => Outcome::Drop,
+    }
+}
+
+/// Answer a request received on a reserved port with the given handler. The reply uses the
+/// same options as the request. Requests for which the handler returns [ErrorCode::NotSup] are
+/// passed on to [ffi::csp_service_handler], and requests which failed otherwise are dropped.
+pub fn dispatch(handler: &mut (impl ServiceHandler + ?Sized), mut packet: Packet) {
+    match handle(handler, &mut packet) {
+        Outcome::Reply => {
+            let packet = packet.into_raw();
+            // SAFETY: FFI call. The request is used as the reply and its ownership is passed to
+            // libcsp.
+            unsafe {
+                ffi::csp_sendto_reply(packet, packet, ConnectOpts::SAME.bits());
+            }
+        }
+        // SAFETY: FFI call. The service handler takes ownership of the packet.
+        Outcome::Fallback => unsafe { ffi::csp_service_handler(packet.into_raw()) },
+        Outcome::Drop => drop(packet),
+    }
+}
+
+/// Handler installed with [install]. The handler has its own lock, so it can be invoked without
+/// holding the lock of the installed handler, and may call [install] and [uninstall] itself.
+#[cfg(feature = "std")]
+type InstalledHandler = Arc<Mutex<dyn ServiceHandler + Send>>;
+
+#[cfg(feature = "std")]
+static INSTALLED: Mutex<Option<InstalledHandler>> = Mutex::new(None);
+
+/// Install a global service handler which is used by [crate::csp_service_handler]. Returns
+/// [true] if a previously installed handler was replaced.
+///
+/// A request which is already dispatched to the previous handler is completed.
+#[cfg(feature = "std")]
+pub fn install(handler: impl ServiceHandler + Send + 'static) -> bool {
+    INSTALLED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .replace(Arc::new(Mutex::new(handler)))
+        .is_some()
+}
+
+/// Remove the global service handler, so [crate::csp_service_handler] only uses the C
+/// implementation again. Returns [false] if no handler was installed.
+#[cfg(feature = "std")]
+pub fn uninstall() -> bool {
+    INSTALLED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take()
+        .is_some()
+}
+
+/// Dispatch a packet to the installed service handler. Returns the packet if no handler is
+/// installed.
+#[cfg(feature = "std")]
+pub(crate) fn dispatch_installed(packet: Packet) -> Option<Packet> {
+    let Some(handler) = INSTALLED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+    else {
+        return Some(packet);
+    };
+    dispatch(
+        &mut *handler.lock().unwrap_or_else(PoisonError::into_inner),
+        packet,
+    );
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CspId, HeaderFlags, CSP_ANY};
+    use core::mem::ManuallyDrop;
+    use std::{boxed::Box, vec, vec::Vec};
+
+    /// Handler which answers every service and records the invoked methods.
+    #[derive(Default)]
+    struct Recording {
+        calls: Vec<&'static str>,
+    }
+
+    impl ServiceHandler for Recording {
+        fn ping(&mut self, _request: &Packet) -> Result<(), CspError> {
+            self.calls.push("ping");
+            Ok(())
+        }
+
+        fn ps(&mut self, reply: &mut Packet) -> Result<(), CspError> {
+            self.calls.push("ps");
+            reply.set_data(b"task")
+        }
+
+        fn memfree(&mut self) -> Result<u32, CspError> {
+            self.calls.push("memfree");
+            Ok(0x0102_0304)
+        }
+
+        fn reboot(&mut self) -> Result<(), CspError> {
+            self.calls.push("reboot");
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> Result<(), CspError> {
+            self.calls.push("shutdown");
+            Ok(())
+        }
+
+        fn buf_free(&mut self) -> Result<u32, CspError> {
+            self.calls.push("buf_free");
+            Ok(12)
+        }
+
+        fn uptime(&mut self) -> Result<Duration, CspError> {
+            self.calls.push("uptime");
+            Ok(Duration::from_millis(70_900))
+        }
+    }
+
+    /// Pass a request to the handler. Returns the outcome and the packet payload afterwards.
+    fn request(
+        handler: &mut impl ServiceHandler,
+        port: ReservedPort,
+        data: &[u8],
+    ) -> (Outcome, Vec<u8>) {
+        request_on(handler, port as u8, data)
+    }
+
+    fn request_on(handler: &mut impl ServiceHandler, dport: u8, data: &[u8]) -> (Outcome, Vec<u8>) {
+        // SAFETY: The packet only consists of integers and integer arrays.
+        let mut raw: Box<ffi::csp_packet_t> = Box::new(unsafe { core::mem::zeroed() });
+        // The packet is not allocated from the buffer pool, so it must not be dropped.
+        // SAFETY: The pointer is valid and not used anywhere else while the packet exists.
+        let mut packet = ManuallyDrop::new(unsafe { Packet::from_raw(&mut *raw) }.unwrap());
+        packet.set_id(CspId {
+            prio: MsgPriority::Normal,
+            flags: HeaderFlags::empty(),
+            src: 1,
+            dst: 2,
+            dport,
+            sport: 20,
+        });
+        packet.set_data(data).unwrap();
+        let outcome = handle(&mut *handler, &mut packet);
+        (outcome, packet.to_vec())
+    }
+
+    #[test]
+    fn u32_replies_are_big_endian() {
+        let mut handler = Recording::default();
+        assert_eq!(
+            request(&mut handler, ReservedPort::Memfree, &[]),
+            (Outcome::Reply, vec![1, 2, 3, 4])
+        );
+        assert_eq!(
+            request(&mut handler, ReservedPort::BufFree, &[]),
+            (Outcome::Reply, vec![0, 0, 0, 12])
+        );
+        // The uptime is sent in whole seconds.
+        assert_eq!(
+            request(&mut handler, ReservedPort::Uptime, &[]),
+            (Outcome::Reply, vec![0, 0, 0, 70])
+        );
+        assert_eq!(handler.calls, ["memfree", "buf_free", "uptime"]);
+    }
+
+    #[test]
+    fn reboot_magic() {
+        let mut handler = Recording::default();
+        let reboot = ffi::CSP_REBOOT_MAGIC.to_be_bytes();
+        let shutdown = ffi::CSP_REBOOT_SHUTDOWN_MAGIC.to_be_bytes();
+        assert_eq!(
+            request(&mut handler, ReservedPort::Reboot, &reboot).0,
+            Outcome::Drop
+        );
+        assert_eq!(
+            request(&mut handler, ReservedPort::Reboot, &shutdown).0,
+            Outcome::Drop
+        );
+        assert_eq!(handler.calls, ["reboot", "shutdown"]);
+
+        // Requests with an invalid magic are dropped without invoking the handler.
+        let little_endian = ffi::CSP_REBOOT_MAGIC.to_le_bytes();
+        for data in [&little_endian[..], &reboot[..3], &[]] {
+            assert_eq!(
+                request(&mut handler, ReservedPort::Reboot, data).0,
+                Outcome::Drop
+            );
+        }
+        assert_eq!(handler.calls, ["reboot", "shutdown"]);
+    }
+
+    /// Handler which only implements ping and fails the memfree request.
+    struct Partial;
+
+    impl ServiceHandler for Partial {
+        fn ping(&mut self, _request: &Packet) -> Result<(), CspError> {
+            Ok(())
+        }
+
+        fn memfree(&mut self) -> Result<u32, CspError> {
+            Err(CspError::new("memfree", ErrorCode::NoMem))
+        }
+    }
+
+    #[test]
+    fn not_supported_falls_back_to_c() {
+        let mut handler = Partial;
+        assert_eq!(
+            request(&mut handler, ReservedPort::Ping, b"ping"),
+            (Outcome::Reply, b"ping".to_vec())
+        );
+        // Other errors drop the request.
+        assert_eq!(
+            request(&mut handler, ReservedPort::Memfree, &[]).0,
+            Outcome::Drop
+        );
+        // The default methods return NotSup, so the C implementation answers the request.
+        let reboot = ffi::CSP_REBOOT_MAGIC.to_be_bytes();
+        for (port, data) in [
+            (ReservedPort::Ps, &[0x55][..]),
+            (ReservedPort::Reboot, &reboot[..]),
+            (ReservedPort::BufFree, &[]),
+            (ReservedPort::Uptime, &[]),
+        ] {
+            assert_eq!(request(&mut handler, port, data).0, Outcome::Fallback);
+        }
+    }
+
+    #[test]
+    fn handled_ports() {
+        let mut handler = Recording::default();
+        assert_eq!(
+            request(&mut handler, ReservedPort::Ping, b"ping"),
+            (Outcome::Reply, b"ping".to_vec())
+        );
+        // The process list is written into the cleared request and terminated.
+        assert_eq!(
+            request(&mut handler, ReservedPort::Ps, &[0x55]),
+            (Outcome::Reply, b"task\0".to_vec())
+        );
+        assert_eq!(handler.calls, ["ping", "ps"]);
+
+        // CMP and all other ports are always handled by the C implementation.
+        for dport in [ReservedPort::Cmp as u8, 7, 20, CSP_ANY] {
+            assert_eq!(
+                request_on(&mut handler, dport, b"data"),
+                (Outcome::Fallback, b"data".to_vec())
+            );
+        }
+        assert_eq!(handler.calls, ["ping", "ps"]);
+    }
+}
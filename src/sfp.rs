@@ -0,0 +1,226 @@
+//! Small Fragmentation Protocol (SFP).
+//!
+//! SFP transfers data which does not fit into a single packet over an established connection.
+//! The data is split into chunks of at most `mtu` bytes, and every chunk is sent as one packet
+//! with the [HeaderFlags::FRAG] flag and an SFP header, which contains the offset of the chunk
+//! and the total size of the data.
+//!
+//! SFP relies on the connection to deliver the chunks in order and without losses, so RDP
+//! connections should be used for links which can drop packets. RDP also limits the number of
+//! packets in flight, while [sfp_send] may otherwise exhaust the buffer pool for large
+//! transfers.
+//!
+//! The receiving side is implemented in Rust, because [ffi::csp_sfp_recv_fp] allocates the
+//! data with `malloc`.
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+use core::{ffi::c_uint, time::Duration};
+
+use crate::{csp_read, ffi, timeout_millis, CspConnRef, CspError, ErrorCode, HeaderFlags, Packet};
+
+/// Size of the SFP header which is appended to the chunk in every packet.
+pub const HEADER_SIZE: usize = 8;
+/// Size of the RDP header, which reduces the maximum MTU on RDP connections.
+pub const RDP_HEADER_SIZE: usize = 5;
+/// Maximum MTU on connections without RDP.
+pub const MAX_MTU: usize = Packet::CAPACITY - HEADER_SIZE;
+
+/// SFP header of a received chunk.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+struct Header {
+    offset: usize,
+    total: usize,
+}
+
+/// Split a received packet into the chunk and the SFP header.
+fn parse(flags: HeaderFlags, data: &[u8]) -> Result<(&[u8], Header), CspError> {
+    if !flags.contains(HeaderFlags::FRAG) || data.len() < HEADER_SIZE {
+        return Err(CspError::new("csp_sfp_recv", ErrorCode::Sfp));
+    }
+    let (chunk, header) = data.split_at(data.len() - HEADER_SIZE);
+    let offset = u32::from_be_bytes(header[..4].try_into().unwrap());
+    let total = u32::from_be_bytes(header[4..].try_into().unwrap());
+    Ok((
+        chunk,
+        Header {
+            offset: offset as usize,
+            total: total as usize,
+        },
+    ))
+}
+
+fn read_chunk(conn: &mut CspConnRef, timeout: Duration) -> Result<(Packet, Header), CspError> {
+    let packet = csp_read(conn, timeout)?;
+    let (_, header) = parse(packet.id().flags, &packet)?;
+    Ok((packet, header))
+}
+
+/// Copy the chunks of a transfer into `buf`, which has the total size of the transfer,
+/// starting with the already received first chunk.
+fn recv_chunks(
+    conn: &mut CspConnRef,
+    mut packet: Packet,
+    buf: &mut [u8],
+    timeout: Duration,
+) -> Result<(), CspError> {
+    let mut received = 0;
+    loop {
+        let (chunk, header) = parse(packet.id().flags, &packet)?;
+        if header.offset != received
+            || header.total != buf.len()
+            || chunk.len() > buf.len() - received
+        {
+            return Err(CspError::new("csp_sfp_recv", ErrorCode::Sfp));
+        }
+        buf[received..received + chunk.len()].copy_from_slice(chunk);
+        received += chunk.len();
+        if received == buf.len() {
+            return Ok(());
+        }
+        packet = read_chunk(conn, timeout)?.0;
+    }
+}
+
+/// Largest MTU for [sfp_send] on the connection. The chunk and the SFP header must fit into a
+/// packet, which also contains the RDP header on RDP connections.
+pub fn max_mtu(conn: &mut CspConnRef) -> usize {
+    let rdp = conn.inner().is_some_and(|conn| {
+        HeaderFlags::from_bits_retain(conn.idout.flags).contains(HeaderFlags::RDP)
+    });
+    if rdp {
+        MAX_MTU - RDP_HEADER_SIZE
+    } else {
+        MAX_MTU
+    }
+}
+
+/// Rust wrapper for [ffi::csp_sfp_send_own_memcpy].
+///
+/// Sends `data` in chunks of at most `mtu` bytes, which must be at most [max_mtu] of the
+/// connection. Empty data is not sent at all. `timeout` is not used by `libcsp`.
+///
+/// Returns [ErrorCode::Inval] if `mtu` is 0 or too large, or if the data is larger than 4 GiB,
+/// and [ErrorCode::NoMem] if no buffer was available for a chunk. The chunks which were sent
+/// before an error can not be taken back.
+pub fn sfp_send(
+    conn: &mut CspConnRef,
+    data: &[u8],
+    mtu: usize,
+    timeout: Duration,
+) -> Result<(), CspError> {
+    let (Ok(datasize), Ok(mtu)) = (c_uint::try_from(data.len()), c_uint::try_from(mtu)) else {
+        return Err(CspError::new("csp_sfp_send", ErrorCode::Inval));
+    };
+    if mtu == 0 || mtu as usize > max_mtu(conn) {
+        return Err(CspError::new("csp_sfp_send", ErrorCode::Inval));
+    }
+    // SAFETY: FFI call. The data is valid for its length, and libcsp copies it into the
+    // packets with memcpy before returning.
+    let result = unsafe {
+        ffi::csp_sfp_send_own_memcpy(
+            conn.0,
+            data.as_ptr().cast(),
+            datasize,
+            mtu,
+            timeout_millis(timeout),
+            Some(libc::memcpy),
+        )
+    };
+    CspError::check("csp_sfp_send", result)
+}
+
+/// Receive an SFP transfer into `buf`. Returns the size of the received data.
+///
+/// `timeout` applies to every chunk. Returns [ErrorCode::TimedOut] if a chunk did not arrive
+/// in time, [ErrorCode::NoMem] if the transfer does not fit into `buf` and [ErrorCode::Sfp] if
+/// a packet without SFP header or an unexpected chunk was received. The remaining chunks of a
+/// failed transfer stay in the connection queue.
+pub fn sfp_recv_into(
+    conn: &mut CspConnRef,
+    buf: &mut [u8],
+    timeout: Duration,
+) -> Result<usize, CspError> {
+    let (packet, header) = read_chunk(conn, timeout)?;
+    let Some(buf) = buf.get_mut(..header.total) else {
+        return Err(CspError::new("csp_sfp_recv", ErrorCode::NoMem));
+    };
+    recv_chunks(conn, packet, buf, timeout)?;
+    Ok(header.total)
+}
+
+/// Receive an SFP transfer into a new vector.
+///
+/// The vector is allocated with the total size announced by the sender, which can be up to
+/// 4 GiB. Use [sfp_recv_max] to limit the size for untrusted senders.
+///
+/// `timeout` applies to every chunk. Returns [ErrorCode::TimedOut] if a chunk did not arrive
+/// in time and [ErrorCode::Sfp] if a packet without SFP header or an unexpected chunk was
+/// received. The remaining chunks of a failed transfer stay in the connection queue.
+#[cfg(feature = "alloc")]
+pub fn sfp_recv(conn: &mut CspConnRef, timeout: Duration) -> Result<Vec<u8>, CspError> {
+    sfp_recv_max(conn, usize::MAX, timeout)
+}
+
+/// Receive an SFP transfer of at most `max_len` bytes into a new vector.
+///
+/// Like [sfp_recv], but returns [ErrorCode::Sfp] without allocating if the sender announced
+/// more than `max_len` bytes.
+#[cfg(feature = "alloc")]
+pub fn sfp_recv_max(
+    conn: &mut CspConnRef,
+    max_len: usize,
+    timeout: Duration,
+) -> Result<Vec<u8>, CspError> {
+    let (packet, header) = read_chunk(conn, timeout)?;
+    if header.total > max_len {
+        return Err(CspError::new("csp_sfp_recv", ErrorCode::Sfp));
+    }
+    let mut data = vec![0; header.total];
+    recv_chunks(conn, packet, &mut data, timeout)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_parsing() {
+        let data = [1, 2, 3, 0, 0, 0x01, 0x00, 0, 0, 0x10, 0x00];
+        assert_eq!(
+            parse(HeaderFlags::FRAG, &data),
+            Ok((
+                &data[..3],
+                Header {
+                    offset: 256,
+                    total: 4096
+                }
+            ))
+        );
+        assert_eq!(
+            parse(HeaderFlags::FRAG, &data[3..]).unwrap().0,
+            &[] as &[u8]
+        );
+        assert_eq!(
+            parse(HeaderFlags::empty(), &data).unwrap_err().code(),
+            ErrorCode::Sfp
+        );
+        assert_eq!(
+            parse(HeaderFlags::FRAG, &data[4..]).unwrap_err().code(),
+            ErrorCode::Sfp
+        );
+    }
+
+    #[test]
+    fn mtu_limit() {
+        // SAFETY: The connection only consists of integers, pointers and optional function
+        // pointers, which may be zero.
+        let mut conn: ffi::csp_conn_t = unsafe { core::mem::zeroed() };
+        assert_eq!(max_mtu(&mut CspConnRef(&mut conn)), MAX_MTU);
+        conn.idout.flags = HeaderFlags::RDP.bits();
+        assert_eq!(
+            max_mtu(&mut CspConnRef(&mut conn)),
+            MAX_MTU - RDP_HEADER_SIZE
+        );
+    }
+}
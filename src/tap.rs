@@ -0,0 +1,155 @@
+//! Packet tap on top of the `libcsp` input and output hooks.
+//!
+//! Observers subscribe with [subscribe] and are invoked for every packet which is received or
+//! sent on any interface. The observers only get read access to the packet, so the routing path
+//! is not modified. They are invoked from the `libcsp` tasks and should return quickly, for
+//! example by only logging the packet or updating counters.
+//!
+//! The tap requires the `hooks` feature, because it is driven by the hook symbols defined in
+//! [crate::hooks]. Observers are invoked in addition to the installed [crate::hooks::Hooks].
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, PoisonError, RwLock,
+    },
+    vec::Vec,
+};
+
+use crate::CspId;
+
+/// Direction of a tapped packet.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// Packet observed by the tap.
+#[derive(Debug, Copy, Clone)]
+pub struct TapEvent<'a> {
+    pub direction: Direction,
+    /// Decoded CSP header. For outbound packets, this is the header which is sent.
+    pub id: CspId,
+    /// Name of the interface the packet was received or is sent on. Empty if the interface has
+    /// no name.
+    pub iface: &'a str,
+    /// Address of the next hop for outbound packets.
+    pub via: Option<u16>,
+    /// Set for outbound packets which originate from this node.
+    pub from_me: bool,
+    /// Packet payload.
+    pub data: &'a [u8],
+}
+
+type Observer = Arc<dyn Fn(&TapEvent) + Send + Sync>;
+
+static OBSERVERS: RwLock<Vec<(u64, Observer)>> = RwLock::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Handle of a tap observer. The observer is unsubscribed when the handle is dropped.
+#[must_use = "the observer is unsubscribed when the subscription is dropped"]
+#[derive(Debug)]
+pub struct Subscription(u64);
+
+impl Subscription {
+    /// Keep the observer subscribed for the rest of the program lifetime.
+    pub fn detach(self) {
+        core::mem::forget(self);
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        OBSERVERS
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(id, _)| *id != self.0);
+    }
+}
+
+/// Subscribe an observer which is invoked for every inbound and outbound packet.
+pub fn subscribe(observer: impl Fn(&TapEvent) + Send + Sync + 'static) -> Subscription {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    OBSERVERS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push((id, Arc::new(observer)));
+    Subscription(id)
+}
+
+/// Number of subscribed observers.
+pub fn observer_count() -> usize {
+    OBSERVERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .len()
+}
+
+/// Invoke all observers with the given event. The observer list is not locked while the
+/// observers are invoked, so they can subscribe and unsubscribe observers themselves.
+pub(crate) fn notify(event: &TapEvent) {
+    let observers: Vec<Observer> = OBSERVERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|(_, observer)| observer.clone())
+        .collect();
+    for observer in observers {
+        observer(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{HeaderFlags, MsgPriority};
+    use std::sync::{atomic::AtomicUsize, Mutex};
+
+    #[test]
+    fn subscribe_and_notify() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_observer = count.clone();
+        let subscription = subscribe(move |event| {
+            assert_eq!(event.direction, Direction::Inbound);
+            assert_eq!(event.iface, "LOOP");
+            assert_eq!(event.data, &[1, 2, 3]);
+            count_observer.fetch_add(1, Ordering::Relaxed);
+        });
+        let event = TapEvent {
+            direction: Direction::Inbound,
+            id: CspId {
+                prio: MsgPriority::Normal,
+                flags: HeaderFlags::empty(),
+                src: 1,
+                dst: 2,
+                dport: 10,
+                sport: 20,
+            },
+            iface: "LOOP",
+            via: None,
+            from_me: false,
+            data: &[1, 2, 3],
+        };
+        notify(&event);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+        drop(subscription);
+        notify(&event);
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+
+        // Observers can subscribe and unsubscribe observers while they are invoked.
+        let nested = Arc::new(Mutex::new(Vec::new()));
+        let nested_observer = nested.clone();
+        let subscription = subscribe(move |_| {
+            let mut nested = nested_observer.lock().unwrap();
+            nested.clear();
+            nested.push(subscribe(|_| {}));
+        });
+        notify(&event);
+        assert_eq!(observer_count(), 2);
+        notify(&event);
+        assert_eq!(observer_count(), 2);
+        drop(subscription);
+        nested.lock().unwrap().clear();
+        assert_eq!(observer_count(), 0);
+    }
+}
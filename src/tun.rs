@@ -0,0 +1,90 @@
+//! TUN interface for encrypted tunnels.
+//!
+//! Packets which are routed to a TUN interface are encrypted with the `csp_crypto_encrypt` hook
+//! and sent as the payload of a new packet from the tunnel source to the tunnel destination.
+//! Tunneled packets which are addressed to the tunnel source are routed to the TUN interface as
+//! well, decrypted with the `csp_crypto_decrypt` hook and passed to the router again.
+//!
+//! The crypto hooks must be provided by the application. With the `hooks` and `crypto` features,
+//! they use the `crypto::CryptoProvider` installed with `crypto::install`. `libcsp` passes at
+//! most 255 bytes to the hooks, and the ciphertext must fit into a packet, so the provider
+//! overhead reduces the maximum payload of tunneled packets.
+use std::{boxed::Box, ffi::CString};
+
+use crate::{driver::new_iface_name, ffi, iflist::IfaceRef, CspError, CspStack};
+
+/// Addresses of a tunnel.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TunConfig {
+    /// Source address of outgoing tunneled packets. Incoming tunneled packets must be addressed
+    /// to it and routed to the TUN interface.
+    pub src: u16,
+    /// Address of the remote tunnel endpoint.
+    pub dst: u16,
+}
+
+struct Interface {
+    iface: ffi::csp_iface_t,
+    conf: ffi::tun::csp_if_tun_conf_t,
+    // Referenced by the interface.
+    _name: CString,
+}
+
+/// TUN interface handle.
+///
+/// The interface stays in the interface list for the rest of the program lifetime.
+#[derive(Debug, Copy, Clone)]
+pub struct TunInterface {
+    iface: IfaceRef,
+    config: TunConfig,
+}
+
+impl TunInterface {
+    /// Rust wrapper for [ffi::tun::csp_if_tun_init].
+    ///
+    /// `netmask` is the number of bits of the subnet mask. Returns [crate::ErrorCode::Inval] if
+    /// the name is empty, longer than [ffi::CSP_IFLIST_NAME_MAX] or contains a NUL character,
+    /// and [crate::ErrorCode::Already] if an interface with the same name exists.
+    pub fn new(
+        _stack: &CspStack,
+        name: &str,
+        addr: u16,
+        netmask: u16,
+        is_default: bool,
+        config: TunConfig,
+    ) -> Result<Self, CspError> {
+        let name = new_iface_name("csp_if_tun_init", name)?;
+        let interface = Box::leak(Box::new(Interface {
+            iface: ffi::csp_iface_t {
+                addr,
+                netmask,
+                name: name.as_ptr(),
+                is_default: is_default as u8,
+                ..Default::default()
+            },
+            conf: ffi::tun::csp_if_tun_conf_t {
+                tun_src: config.src,
+                tun_dst: config.dst,
+            },
+            _name: name,
+        }));
+        // SAFETY: FFI call. The interface and its configuration are leaked, so they are valid
+        // for the rest of the program lifetime. The name was checked above, so adding the
+        // interface to the interface list can not fail.
+        unsafe { ffi::tun::csp_if_tun_init(&mut interface.iface, &mut interface.conf) };
+        Ok(Self {
+            // The pointer is derived from a reference and therefore not null.
+            iface: IfaceRef::from_raw(&mut interface.iface).unwrap(),
+            config,
+        })
+    }
+
+    /// View of the interface, for example to read its statistics.
+    pub fn iface(&self) -> IfaceRef {
+        self.iface
+    }
+
+    pub fn config(&self) -> TunConfig {
+        self.config
+    }
+}
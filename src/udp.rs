@@ -0,0 +1,214 @@
+//! UDP interface.
+//!
+//! [UdpInterface::builder] configures a `libcsp` UDP interface, which sends every packet as one
+//! UDP datagram to the peer and receives datagrams on a local port with its own RX thread. The
+//! configuration and the C strings referenced by the interface are kept alive for the rest of
+//! the program lifetime.
+use core::{ffi::c_int, fmt::Write};
+use std::{
+    boxed::Box,
+    ffi::CString,
+    net::{SocketAddr, SocketAddrV4},
+    string::String,
+};
+
+use crate::{
+    driver::new_iface_name, ffi, iflist::IfaceRef, iflist::IfaceStats, CspError, CspStack,
+    ErrorCode,
+};
+
+/// Default local port of the `libcsp` UDP interface.
+pub const DEFAULT_PORT: u16 = 9600;
+
+struct Interface {
+    iface: ffi::csp_iface_t,
+    conf: ffi::csp_if_udp_conf_t,
+    // Referenced by the interface and its configuration.
+    _name: CString,
+    _host: CString,
+}
+
+const OP: &str = "csp_if_udp_init";
+
+fn check_peer(peer: Option<SocketAddr>) -> Result<SocketAddrV4, CspError> {
+    match peer {
+        Some(SocketAddr::V4(peer)) if peer.port() != 0 => Ok(peer),
+        Some(SocketAddr::V6(_)) => Err(CspError::new(OP, ErrorCode::NotSup)),
+        _ => Err(CspError::new(OP, ErrorCode::Inval)),
+    }
+}
+
+/// Builder for a [UdpInterface].
+#[derive(Debug, Clone)]
+pub struct UdpInterfaceBuilder {
+    peer: Option<SocketAddr>,
+    local_port: u16,
+    name: String,
+    addr: u16,
+    netmask: u16,
+    is_default: bool,
+}
+
+impl Default for UdpInterfaceBuilder {
+    fn default() -> Self {
+        Self {
+            peer: None,
+            local_port: DEFAULT_PORT,
+            name: String::from("UDP"),
+            addr: 0,
+            netmask: 0,
+            is_default: false,
+        }
+    }
+}
+
+impl UdpInterfaceBuilder {
+    /// Address of the peer which receives the transmitted packets. Required.
+    pub fn peer(mut self, peer: SocketAddr) -> Self {
+        self.peer = Some(peer);
+        self
+    }
+
+    /// Local port for received packets. Defaults to [DEFAULT_PORT].
+    pub fn local_port(mut self, port: u16) -> Self {
+        self.local_port = port;
+        self
+    }
+
+    /// Name of the interface. Defaults to `UDP`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Host address on the subnet of the interface.
+    pub fn addr(mut self, addr: u16) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// Number of bits of the subnet mask.
+    pub fn netmask(mut self, netmask: u16) -> Self {
+        self.netmask = netmask;
+        self
+    }
+
+    /// Use the interface for destinations without another route.
+    pub fn default_iface(mut self, is_default: bool) -> Self {
+        self.is_default = is_default;
+        self
+    }
+
+    /// Rust wrapper for [ffi::udp::csp_if_udp_init].
+    ///
+    /// Returns [ErrorCode::Inval] if no peer was set, the peer port is 0 or the name is empty,
+    /// longer than [ffi::CSP_IFLIST_NAME_MAX] or contains a NUL character.
+    /// [ErrorCode::NotSup] is returned for IPv6 peers, and [ErrorCode::Already] if an
+    /// interface with the same name exists.
+    pub fn build(self, _stack: &CspStack) -> Result<UdpInterface, CspError> {
+        let peer = check_peer(self.peer)?;
+        let name = new_iface_name(OP, &self.name)?;
+        let mut host = String::new();
+        // Writing to a string can not fail.
+        let _ = write!(host, "{}", peer.ip());
+        // An IP address never contains a NUL character.
+        let host = CString::new(host).unwrap();
+        let interface = Box::leak(Box::new(Interface {
+            iface: ffi::csp_iface_t {
+                addr: self.addr,
+                netmask: self.netmask,
+                name: name.as_ptr(),
+                is_default: self.is_default as u8,
+                ..Default::default()
+            },
+            conf: ffi::csp_if_udp_conf_t {
+                host: host.as_ptr() as *mut _,
+                lport: c_int::from(self.local_port),
+                rport: c_int::from(peer.port()),
+                ..Default::default()
+            },
+            _name: name,
+            _host: host,
+        }));
+        // SAFETY: FFI call. The interface, its configuration and the strings are leaked, so
+        // they are valid for the rest of the program lifetime. libcsp never writes to the host
+        // string.
+        unsafe { ffi::udp::csp_if_udp_init(&mut interface.iface, &mut interface.conf) };
+        Ok(UdpInterface {
+            // The pointer is derived from a reference and therefore not null.
+            iface: IfaceRef::from_raw(&mut interface.iface).unwrap(),
+            peer,
+            local_port: self.local_port,
+        })
+    }
+}
+
+/// UDP interface handle, created with [UdpInterface::builder].
+///
+/// [UdpInterface::remove] consumes the handle, so the interface can not be removed twice with
+/// it. [IfaceRef] views of the interface, returned by [UdpInterface::iface] or
+/// [crate::iflist::iter], stay valid after the removal because the interface is never freed.
+#[derive(Debug)]
+pub struct UdpInterface {
+    iface: IfaceRef,
+    peer: SocketAddrV4,
+    local_port: u16,
+}
+
+impl UdpInterface {
+    pub fn builder() -> UdpInterfaceBuilder {
+        UdpInterfaceBuilder::default()
+    }
+
+    /// View of the interface.
+    pub fn iface(&self) -> IfaceRef {
+        self.iface
+    }
+
+    pub fn stats(&self) -> IfaceStats {
+        self.iface.stats()
+    }
+
+    pub fn peer(&self) -> SocketAddrV4 {
+        self.peer
+    }
+
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+
+    /// Remove the interface from the interface list, so no packets are routed to it anymore.
+    ///
+    /// `libcsp` can not stop the RX thread of the interface, so received datagrams are still
+    /// passed to the router.
+    pub fn remove(self, stack: &CspStack) {
+        crate::iflist::remove(stack, self.iface)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv6Addr, SocketAddrV6};
+
+    #[test]
+    fn peer_validation() {
+        let peer = "127.0.0.1:9600".parse().unwrap();
+        assert_eq!(
+            check_peer(Some(peer)),
+            Ok("127.0.0.1:9600".parse().unwrap())
+        );
+        assert_eq!(check_peer(None).unwrap_err().code(), ErrorCode::Inval);
+        assert_eq!(
+            check_peer(Some("127.0.0.1:0".parse().unwrap()))
+                .unwrap_err()
+                .code(),
+            ErrorCode::Inval
+        );
+        let peer = SocketAddrV6::new(Ipv6Addr::LOCALHOST, 9600, 0, 0);
+        assert_eq!(
+            check_peer(Some(peer.into())).unwrap_err().code(),
+            ErrorCode::NotSup
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! ZeroMQ hub interface.
+//!
+//! [ZmqInterface::builder] adds a `libcsp` ZMQ hub interface, which connects to a ZMQ proxy like
+//! the `zmqproxy` of `libcsp`. Every interface publishes its outgoing packets to the subscribe
+//! endpoint of the proxy and subscribes to the packets for its address on the publish endpoint
+//! of the proxy, so many CSP processes can be connected through one proxy. The interface must
+//! be enabled with the `zmq` option of the `libcsp-cargo-build` configuration.
+use core::ffi::c_int;
+use std::{ffi::CString, format, string::String};
+
+use crate::{
+    driver::{configure_added_iface, new_iface_name},
+    ffi,
+    iflist::IfaceRef,
+    iflist::IfaceStats,
+    CspError, CspStack, ErrorCode,
+};
+
+/// Default port of the proxy which the interfaces publish their outgoing packets to.
+pub const PROXY_SUBSCRIBE_PORT: u16 = ffi::zmqhub::CSP_ZMQPROXY_SUBSCRIBE_PORT as u16;
+/// Default port of the proxy which the interfaces subscribe to for incoming packets.
+pub const PROXY_PUBLISH_PORT: u16 = ffi::zmqhub::CSP_ZMQPROXY_PUBLISH_PORT as u16;
+
+const OP: &str = "csp_zmqhub_init_w_name_endpoints_rxfilter";
+
+/// TCP endpoint for the given host and port, in the format used by
+/// [ffi::zmqhub::csp_zmqhub_make_endpoint].
+pub fn tcp_endpoint(host: &str, port: u16) -> String {
+    format!("tcp://{}:{}", host, port)
+}
+
+/// Endpoints have the `transport://address` format. `libcsp` asserts that connecting to the
+/// endpoint succeeds, so obviously invalid endpoints are rejected before.
+fn check_endpoint(endpoint: Option<&str>) -> Result<CString, CspError> {
+    let endpoint = endpoint.ok_or(CspError::new(OP, ErrorCode::Inval))?;
+    match endpoint.split_once("://") {
+        Some((transport, address)) if !transport.is_empty() && !address.is_empty() => {
+            CString::new(endpoint).map_err(|_| CspError::new(OP, ErrorCode::Inval))
+        }
+        _ => Err(CspError::new(OP, ErrorCode::Inval)),
+    }
+}
+
+/// Builder for a [ZmqInterface].
+#[derive(Debug, Clone)]
+pub struct ZmqInterfaceBuilder {
+    publish_endpoint: Option<String>,
+    subscribe_endpoint: Option<String>,
+    name: String,
+    addr: u16,
+    netmask: u16,
+    is_default: bool,
+    promisc: bool,
+}
+
+impl Default for ZmqInterfaceBuilder {
+    fn default() -> Self {
+        Self {
+            publish_endpoint: None,
+            subscribe_endpoint: None,
+            name: String::from("ZMQHUB"),
+            addr: 0,
+            netmask: 0,
+            is_default: false,
+            promisc: false,
+        }
+    }
+}
+
+impl ZmqInterfaceBuilder {
+    /// Connect to a proxy on the given host which uses the default ports
+    /// [PROXY_SUBSCRIBE_PORT] and [PROXY_PUBLISH_PORT].
+    pub fn proxy(self, host: &str) -> Self {
+        self.publish_endpoint(tcp_endpoint(host, PROXY_SUBSCRIBE_PORT))
+            .subscribe_endpoint(tcp_endpoint(host, PROXY_PUBLISH_PORT))
+    }
+
+    /// Endpoint which outgoing packets are published to, usually the subscribe endpoint of
+    /// the proxy. Required.
+    pub fn publish_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.publish_endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Endpoint which incoming packets are subscribed from, usually the publish endpoint of
+    /// the proxy. Required.
+    pub fn subscribe_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.subscribe_endpoint = Some(endpoint.into());
+        self
+    }
+
+    /// Name of the interface. Defaults to `ZMQHUB`.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Host address on the subnet of the interface.
+    pub fn addr(mut self, addr: u16) -> Self {
+        self.addr = addr;
+        self
+    }
+
+    /// Number of bits of the subnet mask.
+    pub fn netmask(mut self, netmask: u16) -> Self {
+        self.netmask = netmask;
+        self
+    }
+
+    /// Use the interface for destinations without another route.
+    pub fn default_iface(mut self, is_default: bool) -> Self {
+        self.is_default = is_default;
+        self
+    }
+
+    /// Subscribe to all packets instead of only the packets addressed to the interface.
+    pub fn promisc(mut self, promisc: bool) -> Self {
+        self.promisc = promisc;
+        self
+    }
+
+    /// Rust wrapper for [ffi::zmqhub::csp_zmqhub_init_w_name_endpoints_rxfilter].
+    ///
+    /// Returns [ErrorCode::Inval] if an endpoint was not set or is not in the
+    /// `transport://address` format, or if the name is empty, longer than
+    /// [ffi::CSP_IFLIST_NAME_MAX] or contains a NUL character. [ErrorCode::Already] is returned
+    /// if an interface with the same name exists.
+    ///
+    /// `libcsp` asserts that the ZMQ sockets can be created and connected, so an endpoint which
+    /// is rejected by ZMQ aborts the program.
+    pub fn build(self, _stack: &CspStack) -> Result<ZmqInterface, CspError> {
+        let publish = check_endpoint(self.publish_endpoint.as_deref())?;
+        let subscribe = check_endpoint(self.subscribe_endpoint.as_deref())?;
+        // The driver copies the name into its own context.
+        let name = new_iface_name(OP, &self.name)?;
+        let mut iface = core::ptr::null_mut();
+        // SAFETY: FFI call with valid strings and a valid output pointer. The endpoints are
+        // only used while connecting the sockets.
+        let result = unsafe {
+            ffi::zmqhub::csp_zmqhub_init_w_name_endpoints_rxfilter(
+                name.as_ptr(),
+                self.addr,
+                publish.as_ptr(),
+                subscribe.as_ptr(),
+                c_int::from(self.promisc),
+                &mut iface,
+            )
+        };
+        CspError::check(OP, result)?;
+        let iface = IfaceRef::from_raw(iface).ok_or(CspError::new(OP, ErrorCode::Driver))?;
+        // SAFETY: The interface was just added by the driver, which never frees it. The driver
+        // only sets the address, so the remaining configuration is applied here.
+        unsafe { configure_added_iface(OP, iface.as_ptr(), self.netmask, self.is_default)? };
+        Ok(ZmqInterface {
+            iface,
+            promisc: self.promisc,
+        })
+    }
+}
+
+/// ZMQ hub interface handle, created with [ZmqInterface::builder].
+///
+/// The interface stays in the interface list for the rest of the program lifetime.
+#[derive(Debug, Copy, Clone)]
+pub struct ZmqInterface {
+    iface: IfaceRef,
+    promisc: bool,
+}
+
+impl ZmqInterface {
+    pub fn builder() -> ZmqInterfaceBuilder {
+        ZmqInterfaceBuilder::default()
+    }
+
+    /// View of the interface.
+    pub fn iface(&self) -> IfaceRef {
+        self.iface
+    }
+
+    pub fn stats(&self) -> IfaceStats {
+        self.iface.stats()
+    }
+
+    pub fn promisc(&self) -> bool {
+        self.promisc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_endpoints() {
+        let builder = ZmqInterface::builder().proxy("localhost");
+        assert_eq!(
+            builder.publish_endpoint.as_deref(),
+            Some("tcp://localhost:6000")
+        );
+        assert_eq!(
+            builder.subscribe_endpoint.as_deref(),
+            Some("tcp://localhost:7000")
+        );
+    }
+
+    #[test]
+    fn endpoint_validation() {
+        assert_eq!(
+            check_endpoint(Some("tcp://127.0.0.1:6000")),
+            Ok(CString::new("tcp://127.0.0.1:6000").unwrap())
+        );
+        assert!(check_endpoint(Some("ipc:///tmp/csp")).is_ok());
+        for endpoint in [
+            None,
+            Some(""),
+            Some("localhost:6000"),
+            Some("tcp://"),
+            Some("://a"),
+        ] {
+            assert_eq!(
+                check_endpoint(endpoint).unwrap_err().code(),
+                ErrorCode::Inval
+            );
+        }
+        assert_eq!(
+            check_endpoint(Some("tcp://a\0b")).unwrap_err().code(),
+            ErrorCode::Inval
+        );
+    }
+}